@@ -0,0 +1,857 @@
+use cosmwasm_std::{
+    log, to_binary, Api, Binary, CosmosMsg, Decimal, Env, Extern, HandleResponse, HandleResult,
+    HumanAddr, InitResponse, Querier, QueryRequest, StdError, StdResult, Storage, WasmMsg,
+    WasmQuery,
+};
+
+use crate::state::{
+    may_read_pending_market, read_config, read_market, remove_pending_market, store_config,
+    store_market, store_pending_market, Config, Market, PendingMarket,
+};
+use scrt_prediction::asset::AssetInfo;
+use scrt_prediction::factory::{
+    ConfigResponse, HandleMsg, InitMsg, MarketResponse, MarketsResponse, QueryMsg,
+};
+use scrt_prediction::hook::InitHook;
+use scrt_prediction::oracle::{
+    ConfigResponse as OracleConfigResponse, InitMsg as OracleInitMsg, QueryMsg as OracleQueryMsg,
+};
+use scrt_prediction::ownership::{accept_transfer, PendingTransfer};
+use scrt_prediction::prediction::{
+    ConfigResponse as PredictionConfigResponse, FeeRecipientInfo, HandleMsg as PredictionHandleMsg,
+    InitMsg as PredictionInitMsg, MarketParams, QueryMsg as PredictionQueryMsg,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    let config = Config {
+        owner_addr: deps.api.canonical_address(&env.message.sender)?,
+        operator_addr: deps.api.canonical_address(&msg.operator_addr)?,
+        treasury_addr: deps.api.canonical_address(&msg.treasury_addr)?,
+        oracle_code_id: msg.oracle_code_id,
+        oracle_code_hash: msg.oracle_code_hash,
+        prediction_code_id: msg.prediction_code_id,
+        prediction_code_hash: msg.prediction_code_hash,
+        band_oracle: deps.api.canonical_address(&msg.band_oracle)?,
+        band_oracle_code_hash: msg.band_oracle_code_hash,
+        market_count: 0,
+        pending_owner: None,
+    };
+
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> HandleResult {
+    match msg {
+        HandleMsg::UpdateConfig {
+            operator_addr,
+            treasury_addr,
+            oracle_code_id,
+            oracle_code_hash,
+            prediction_code_id,
+            prediction_code_hash,
+            band_oracle,
+            band_oracle_code_hash,
+        } => update_config(
+            deps,
+            env,
+            operator_addr,
+            treasury_addr,
+            oracle_code_id,
+            oracle_code_hash,
+            prediction_code_id,
+            prediction_code_hash,
+            band_oracle,
+            band_oracle_code_hash,
+        ),
+        HandleMsg::ProposeOwner { addr, expires_in } => propose_owner(deps, env, addr, expires_in),
+        HandleMsg::AcceptOwnership {} => accept_ownership(deps, env),
+        HandleMsg::CreateMarket {
+            base_symbol,
+            quote_symbol,
+            bet_asset,
+            public_execution,
+            referral_fee_rate,
+            params,
+        } => create_market(
+            deps,
+            env,
+            base_symbol,
+            quote_symbol,
+            bet_asset,
+            public_execution,
+            referral_fee_rate,
+            params,
+        ),
+        HandleMsg::RegisterOracle {} => register_oracle(deps, env),
+        HandleMsg::RegisterPrediction {} => register_prediction(deps, env),
+    }
+}
+
+pub fn update_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    operator_addr: Option<HumanAddr>,
+    treasury_addr: Option<HumanAddr>,
+    oracle_code_id: Option<u64>,
+    oracle_code_hash: Option<String>,
+    prediction_code_id: Option<u64>,
+    prediction_code_hash: Option<String>,
+    band_oracle: Option<HumanAddr>,
+    band_oracle_code_hash: Option<String>,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    if let Some(operator_addr) = operator_addr {
+        config.operator_addr = deps.api.canonical_address(&operator_addr)?;
+    }
+
+    if let Some(treasury_addr) = treasury_addr {
+        config.treasury_addr = deps.api.canonical_address(&treasury_addr)?;
+    }
+
+    if let Some(oracle_code_id) = oracle_code_id {
+        config.oracle_code_id = oracle_code_id;
+        config.oracle_code_hash = oracle_code_hash
+            .ok_or_else(|| StdError::generic_err("Oracle code hash is required"))?;
+    }
+
+    if let Some(prediction_code_id) = prediction_code_id {
+        config.prediction_code_id = prediction_code_id;
+        config.prediction_code_hash = prediction_code_hash
+            .ok_or_else(|| StdError::generic_err("Prediction code hash is required"))?;
+    }
+
+    if let Some(band_oracle) = band_oracle {
+        config.band_oracle = deps.api.canonical_address(&band_oracle)?;
+        config.band_oracle_code_hash = band_oracle_code_hash
+            .ok_or_else(|| StdError::generic_err("Band oracle code hash is required"))?;
+    }
+
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "update_config"), log("status", "success")],
+        data: None,
+    })
+}
+
+pub fn propose_owner<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    addr: HumanAddr,
+    expires_in: Option<u64>,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    let pending_owner = PendingTransfer::new(&env, deps.api.canonical_address(&addr)?, expires_in)?;
+    let expires_at = pending_owner.expires_at;
+    config.pending_owner = Some(pending_owner);
+    store_config(&mut deps.storage, &config)?;
+
+    let mut logs = vec![log("action", "propose_owner"), log("addr", addr)];
+    if let Some(expires_at) = expires_at {
+        logs.push(log("expires_at", expires_at));
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn accept_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    config.owner_addr = accept_transfer(config.pending_owner.take(), &env, sender_raw)?;
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "accept_ownership"),
+            log("owner_addr", env.message.sender),
+        ],
+        data: None,
+    })
+}
+
+/// Instantiate oracle first, prediction is instantiated from oracle init callback
+pub fn create_market<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    base_symbol: String,
+    quote_symbol: String,
+    bet_asset: AssetInfo,
+    public_execution: bool,
+    referral_fee_rate: Decimal,
+    params: MarketParams,
+) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    if may_read_pending_market(&deps.storage)?.is_some() {
+        return Err(StdError::generic_err("Market is being created"));
+    }
+
+    let bet_asset = bet_asset.to_raw(deps)?;
+    store_pending_market(
+        &mut deps.storage,
+        &PendingMarket {
+            base_symbol: base_symbol.clone(),
+            quote_symbol: quote_symbol.clone(),
+            bet_asset,
+            public_execution,
+            referral_fee_rate,
+            params,
+            oracle_addr: None,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Instantiate {
+            code_id: config.oracle_code_id,
+            callback_code_hash: config.oracle_code_hash,
+            msg: to_binary(&OracleInitMsg {
+                band_oracle: deps.api.human_address(&config.band_oracle)?,
+                band_oracle_code_hash: config.band_oracle_code_hash,
+                base_symbol: base_symbol.clone(),
+                quote_symbol: quote_symbol.clone(),
+                init_hook: Some(InitHook {
+                    msg: to_binary(&HandleMsg::RegisterOracle {})?,
+                    contract_addr: env.contract.address.clone(),
+                    code_hash: env.contract_code_hash.clone(),
+                }),
+            })?,
+            send: vec![],
+            label: format!(
+                "{} {}/{} oracle {}",
+                env.contract.address,
+                base_symbol,
+                quote_symbol,
+                config.market_count + 1
+            ),
+        })],
+        log: vec![
+            log("action", "create_market"),
+            log("base_symbol", base_symbol),
+            log("quote_symbol", quote_symbol),
+        ],
+        data: None,
+    })
+}
+
+/// Store oracle address and instantiate prediction with the oracle
+pub fn register_oracle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
+    let mut pending_market = match may_read_pending_market(&deps.storage)? {
+        Some(pending_market) => pending_market,
+        None => return Err(StdError::unauthorized()),
+    };
+
+    if pending_market.oracle_addr.is_some() {
+        return Err(StdError::generic_err("Oracle was already registered"));
+    }
+
+    // sender must be an oracle of the pending market, which runs the oracle code
+    let oracle_addr = env.message.sender;
+    let oracle_config: OracleConfigResponse = deps
+        .querier
+        .query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: oracle_addr.clone(),
+            callback_code_hash: config.oracle_code_hash.clone(),
+            msg: to_binary(&OracleQueryMsg::Config {})?,
+        }))
+        .map_err(|_| StdError::unauthorized())?;
+    if deps.api.canonical_address(&oracle_config.band_oracle)? != config.band_oracle
+        || oracle_config.base_symbol != pending_market.base_symbol
+        || oracle_config.quote_symbol != pending_market.quote_symbol
+    {
+        return Err(StdError::unauthorized());
+    }
+
+    pending_market.oracle_addr = Some(deps.api.canonical_address(&oracle_addr)?);
+    store_pending_market(&mut deps.storage, &pending_market)?;
+
+    Ok(HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Instantiate {
+            code_id: config.prediction_code_id,
+            callback_code_hash: config.prediction_code_hash,
+            msg: to_binary(&PredictionInitMsg {
+                operator_addr: deps.api.human_address(&config.operator_addr)?,
+                fee_recipients: vec![FeeRecipientInfo {
+                    addr: deps.api.human_address(&config.treasury_addr)?,
+                    weight: Decimal::one(),
+                }],
+                public_execution: pending_market.public_execution,
+                referral_fee_rate: pending_market.referral_fee_rate,
+                markets: vec![pending_market.params.into_market_info(
+                    pending_market.bet_asset.to_normal(deps)?,
+                    oracle_addr.clone(),
+                    config.oracle_code_hash,
+                )],
+                init_hook: Some(InitHook {
+                    msg: to_binary(&HandleMsg::RegisterPrediction {})?,
+                    contract_addr: env.contract.address.clone(),
+                    code_hash: env.contract_code_hash.clone(),
+                }),
+            })?,
+            send: vec![],
+            label: format!(
+                "{} {}/{} prediction {}",
+                env.contract.address,
+                pending_market.base_symbol,
+                pending_market.quote_symbol,
+                config.market_count + 1
+            ),
+        })],
+        log: vec![
+            log("action", "register_oracle"),
+            log("oracle_addr", oracle_addr),
+        ],
+        data: None,
+    })
+}
+
+/// Register created market and hand over prediction ownership to the owner
+pub fn register_prediction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+    let pending_market = match may_read_pending_market(&deps.storage)? {
+        Some(pending_market) => pending_market,
+        None => return Err(StdError::unauthorized()),
+    };
+
+    let oracle_addr = match pending_market.oracle_addr {
+        Some(oracle_addr) => oracle_addr,
+        None => return Err(StdError::generic_err("Oracle is not registered")),
+    };
+
+    // sender must be the prediction instantiated by this contract, which is owned by it
+    let prediction_addr = env.message.sender;
+    let prediction_config: PredictionConfigResponse = deps
+        .querier
+        .query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: prediction_addr.clone(),
+            callback_code_hash: config.prediction_code_hash.clone(),
+            msg: to_binary(&PredictionQueryMsg::Config {})?,
+        }))
+        .map_err(|_| StdError::unauthorized())?;
+    if prediction_config.owner_addr != env.contract.address {
+        return Err(StdError::unauthorized());
+    }
+
+    config.market_count += 1;
+    let market_id = config.market_count;
+
+    store_market(
+        &mut deps.storage,
+        market_id,
+        &Market {
+            base_symbol: pending_market.base_symbol,
+            quote_symbol: pending_market.quote_symbol,
+            bet_asset: pending_market.bet_asset,
+            oracle_addr,
+            prediction_addr: deps.api.canonical_address(&prediction_addr)?,
+        },
+    )?;
+    store_config(&mut deps.storage, &config)?;
+    remove_pending_market(&mut deps.storage);
+
+    Ok(HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: prediction_addr.clone(),
+            callback_code_hash: config.prediction_code_hash,
+            msg: to_binary(&PredictionHandleMsg::ProposeOwner {
+                addr: deps.api.human_address(&config.owner_addr)?,
+                expires_in: None,
+            })?,
+            send: vec![],
+        })],
+        log: vec![
+            log("action", "register_prediction"),
+            log("market_id", market_id),
+            log("prediction_addr", prediction_addr),
+        ],
+        data: None,
+    })
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Market { market_id } => to_binary(&query_market(deps, market_id)?),
+        QueryMsg::Markets { start_after, limit } => {
+            to_binary(&query_markets(deps, start_after, limit)?)
+        }
+    }
+}
+
+fn query_config<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ConfigResponse> {
+    let config: Config = read_config(&deps.storage)?;
+    let resp = ConfigResponse {
+        owner_addr: deps.api.human_address(&config.owner_addr)?,
+        operator_addr: deps.api.human_address(&config.operator_addr)?,
+        treasury_addr: deps.api.human_address(&config.treasury_addr)?,
+        oracle_code_id: config.oracle_code_id,
+        oracle_code_hash: config.oracle_code_hash,
+        prediction_code_id: config.prediction_code_id,
+        prediction_code_hash: config.prediction_code_hash,
+        band_oracle: deps.api.human_address(&config.band_oracle)?,
+        band_oracle_code_hash: config.band_oracle_code_hash,
+        market_count: config.market_count,
+        pending_owner: config
+            .pending_owner
+            .map(|pending_owner| pending_owner.to_normal(deps))
+            .transpose()?,
+    };
+
+    Ok(resp)
+}
+
+fn query_market<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+) -> StdResult<MarketResponse> {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    market_response(deps, market_id, market)
+}
+
+fn query_markets<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<MarketsResponse> {
+    let config: Config = read_config(&deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as u64;
+
+    // markets are stored in sequential ids starting from 1
+    let start = start_after
+        .map(|market_id| market_id + 1)
+        .unwrap_or(1)
+        .max(1);
+    let end = (start + limit).min(config.market_count + 1);
+
+    let mut markets: Vec<MarketResponse> = vec![];
+    for market_id in start..end {
+        let market: Market = read_market(&deps.storage, market_id)?;
+        markets.push(market_response(deps, market_id, market)?);
+    }
+
+    Ok(MarketsResponse { markets })
+}
+
+fn market_response<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    market: Market,
+) -> StdResult<MarketResponse> {
+    Ok(MarketResponse {
+        market_id,
+        base_symbol: market.base_symbol,
+        quote_symbol: market.quote_symbol,
+        bet_asset: market.bet_asset.to_normal(deps)?,
+        oracle_addr: deps.api.human_address(&market.oracle_addr)?,
+        prediction_addr: deps.api.human_address(&market.prediction_addr)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+    use cosmwasm_std::{from_binary, from_slice, Empty, QuerierResult, SystemError, Uint128};
+    use scrt_prediction::prediction::{DrawPolicy, FeeMode};
+
+    /// Answers config queries of contracts which run the given code hash
+    struct ContractQuerier {
+        contracts: Vec<(HumanAddr, String, Binary)>,
+    }
+
+    impl Querier for ContractQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+            match request {
+                QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr,
+                    callback_code_hash,
+                    ..
+                }) => match self.contracts.iter().find(|(addr, code_hash, _)| {
+                    *addr == contract_addr && *code_hash == callback_code_hash
+                }) {
+                    Some((_, _, response)) => Ok(Ok(response.clone())),
+                    None => Err(SystemError::NoSuchContract {
+                        addr: contract_addr,
+                    }),
+                },
+                _ => panic!("Unsupported query"),
+            }
+        }
+    }
+
+    fn setup() -> Extern<MockStorage, MockApi, ContractQuerier> {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: ContractQuerier { contracts: vec![] },
+        };
+        init(
+            &mut deps,
+            mock_env("owner", &[]),
+            InitMsg {
+                operator_addr: HumanAddr::from("operator"),
+                treasury_addr: HumanAddr::from("treasury"),
+                oracle_code_id: 1,
+                oracle_code_hash: "oracle_hash".to_string(),
+                prediction_code_id: 2,
+                prediction_code_hash: "prediction_hash".to_string(),
+                band_oracle: HumanAddr::from("band"),
+                band_oracle_code_hash: "band_hash".to_string(),
+            },
+        )
+        .unwrap();
+        deps
+    }
+
+    fn params() -> MarketParams {
+        MarketParams {
+            fee_rate: Decimal::percent(3),
+            fee_mode: FeeMode::OnPool,
+            interval: 300,
+            grace_interval: 30,
+            keeper_delay: 10,
+            keeper_bounty: Uint128(100),
+            min_bet: Uint128(1000),
+            max_bet_per_user: Uint128(0),
+            max_round_pool: Uint128(0),
+            cancel_fee_rate: Decimal::zero(),
+            bucket_bounds: vec![],
+            draw_policy: DrawPolicy::Refund,
+            auto_payout: false,
+        }
+    }
+
+    fn create_market(deps: &mut Extern<MockStorage, MockApi, ContractQuerier>) -> HandleResult {
+        handle(
+            deps,
+            mock_env("owner", &[]),
+            HandleMsg::CreateMarket {
+                base_symbol: "SCRT".to_string(),
+                quote_symbol: "USD".to_string(),
+                bet_asset: AssetInfo::NativeToken {
+                    denom: "uscrt".to_string(),
+                },
+                public_execution: true,
+                referral_fee_rate: Decimal::zero(),
+                params: params(),
+            },
+        )
+    }
+
+    fn add_oracle(
+        deps: &mut Extern<MockStorage, MockApi, ContractQuerier>,
+        addr: &str,
+        quote_symbol: &str,
+    ) {
+        let response = OracleConfigResponse {
+            band_oracle: HumanAddr::from("band"),
+            band_oracle_code_hash: "band_hash".to_string(),
+            base_symbol: "SCRT".to_string(),
+            quote_symbol: quote_symbol.to_string(),
+        };
+        deps.querier.contracts.push((
+            HumanAddr::from(addr),
+            "oracle_hash".to_string(),
+            to_binary(&response).unwrap(),
+        ));
+    }
+
+    fn add_prediction(
+        deps: &mut Extern<MockStorage, MockApi, ContractQuerier>,
+        addr: &str,
+        owner_addr: &str,
+    ) {
+        let response = PredictionConfigResponse {
+            owner_addr: HumanAddr::from(owner_addr),
+            operator_addr: HumanAddr::from("operator"),
+            fee_recipients: vec![],
+            public_execution: true,
+            referral_fee_rate: Decimal::zero(),
+            market_count: 1,
+            pending_owner: None,
+            pending_operator: None,
+        };
+        deps.querier.contracts.push((
+            HumanAddr::from(addr),
+            "prediction_hash".to_string(),
+            to_binary(&response).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn create_market_registers_instantiated_contracts() {
+        let mut deps = setup();
+        let res = create_market(&mut deps).unwrap();
+        match &res.messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, msg, .. }) => {
+                assert_eq!(*code_id, 1);
+                let msg: OracleInitMsg = from_binary(msg).unwrap();
+                assert_eq!(msg.base_symbol, "SCRT");
+                assert_eq!(msg.quote_symbol, "USD");
+            }
+            _ => panic!("Unexpected message"),
+        }
+
+        let err = create_market(&mut deps).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            StdError::generic_err("Market is being created").to_string()
+        );
+
+        add_oracle(&mut deps, "oracle", "USD");
+        let res = handle(
+            &mut deps,
+            mock_env("oracle", &[]),
+            HandleMsg::RegisterOracle {},
+        )
+        .unwrap();
+        match &res.messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, msg, .. }) => {
+                assert_eq!(*code_id, 2);
+                let msg: PredictionInitMsg = from_binary(msg).unwrap();
+                assert_eq!(
+                    msg.markets,
+                    vec![params().into_market_info(
+                        AssetInfo::NativeToken {
+                            denom: "uscrt".to_string()
+                        },
+                        HumanAddr::from("oracle"),
+                        "oracle_hash".to_string(),
+                    )]
+                );
+                assert_eq!(
+                    msg.fee_recipients,
+                    vec![FeeRecipientInfo {
+                        addr: HumanAddr::from("treasury"),
+                        weight: Decimal::one(),
+                    }]
+                );
+            }
+            _ => panic!("Unexpected message"),
+        }
+
+        add_prediction(&mut deps, "prediction", "cosmos2contract");
+        let res = handle(
+            &mut deps,
+            mock_env("prediction", &[]),
+            HandleMsg::RegisterPrediction {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr::from("prediction"),
+                callback_code_hash: "prediction_hash".to_string(),
+                msg: to_binary(&PredictionHandleMsg::ProposeOwner {
+                    addr: HumanAddr::from("owner"),
+                    expires_in: None,
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+
+        let res: MarketsResponse = from_binary(
+            &query(
+                &deps,
+                QueryMsg::Markets {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.markets,
+            vec![MarketResponse {
+                market_id: 1,
+                base_symbol: "SCRT".to_string(),
+                quote_symbol: "USD".to_string(),
+                bet_asset: AssetInfo::NativeToken {
+                    denom: "uscrt".to_string()
+                },
+                oracle_addr: HumanAddr::from("oracle"),
+                prediction_addr: HumanAddr::from("prediction"),
+            }]
+        );
+
+        // next market can be created once the previous one is registered
+        create_market(&mut deps).unwrap();
+    }
+
+    #[test]
+    fn register_rejects_unexpected_contracts() {
+        let mut deps = setup();
+
+        // nothing is being created
+        add_oracle(&mut deps, "oracle", "USD");
+        let res = handle(
+            &mut deps,
+            mock_env("oracle", &[]),
+            HandleMsg::RegisterOracle {},
+        );
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        create_market(&mut deps).unwrap();
+
+        // not a contract running the oracle code
+        let res = handle(
+            &mut deps,
+            mock_env("attacker", &[]),
+            HandleMsg::RegisterOracle {},
+        );
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // oracle of other symbols
+        add_oracle(&mut deps, "eur_oracle", "EUR");
+        let res = handle(
+            &mut deps,
+            mock_env("eur_oracle", &[]),
+            HandleMsg::RegisterOracle {},
+        );
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let res = handle(
+            &mut deps,
+            mock_env("prediction", &[]),
+            HandleMsg::RegisterPrediction {},
+        );
+        match res {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Oracle is not registered"),
+            _ => panic!("Must return generic error"),
+        }
+
+        handle(
+            &mut deps,
+            mock_env("oracle", &[]),
+            HandleMsg::RegisterOracle {},
+        )
+        .unwrap();
+
+        // prediction which was not instantiated by the factory
+        add_prediction(&mut deps, "prediction", "attacker");
+        let res = handle(
+            &mut deps,
+            mock_env("prediction", &[]),
+            HandleMsg::RegisterPrediction {},
+        );
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let res = handle(
+            &mut deps,
+            mock_env("attacker", &[]),
+            HandleMsg::RegisterPrediction {},
+        );
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+    }
+
+    #[test]
+    fn ownership_is_transferred_once_accepted() {
+        let mut deps = setup();
+
+        let msg = HandleMsg::ProposeOwner {
+            addr: HumanAddr::from("new_owner"),
+            expires_in: Some(100),
+        };
+        let res = handle(&mut deps, mock_env("new_owner", &[]), msg.clone());
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+        handle(&mut deps, mock_env("owner", &[]), msg).unwrap();
+
+        let res = handle(
+            &mut deps,
+            mock_env("owner", &[]),
+            HandleMsg::AcceptOwnership {},
+        );
+        match res {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let mut env = mock_env("new_owner", &[]);
+        env.block.time += 100;
+        let res = handle(&mut deps, env.clone(), HandleMsg::AcceptOwnership {});
+        match res {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Transfer expired"),
+            _ => panic!("Must return generic error"),
+        }
+
+        env.block.time -= 1;
+        handle(&mut deps, env, HandleMsg::AcceptOwnership {}).unwrap();
+
+        let config: ConfigResponse =
+            from_binary(&query(&deps, QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(config.owner_addr, HumanAddr::from("new_owner"));
+        assert_eq!(config.pending_owner, None);
+    }
+}
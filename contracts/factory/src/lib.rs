@@ -0,0 +1,5 @@
+pub mod contract;
+mod state;
+
+#[cfg(target_arch = "wasm32")]
+cosmwasm_std::create_entry_points!(contract);
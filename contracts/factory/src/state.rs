@@ -0,0 +1,73 @@
+use cosmwasm_std::{CanonicalAddr, Decimal, StdResult, Storage};
+use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use scrt_prediction::asset::AssetInfoRaw;
+use scrt_prediction::ownership::PendingTransfer;
+use scrt_prediction::prediction::MarketParams;
+
+static KEY_CONFIG: &[u8] = b"config";
+static KEY_PENDING_MARKET: &[u8] = b"pending_market";
+static PREFIX_MARKET: &[u8] = b"market";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub owner_addr: CanonicalAddr,
+    pub operator_addr: CanonicalAddr,
+    pub treasury_addr: CanonicalAddr,
+    pub oracle_code_id: u64,
+    pub oracle_code_hash: String,
+    pub prediction_code_id: u64,
+    pub prediction_code_hash: String,
+    pub band_oracle: CanonicalAddr,
+    pub band_oracle_code_hash: String,
+    pub market_count: u64,
+    /// Proposed owner which has not accepted ownership yet
+    pub pending_owner: Option<PendingTransfer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Market {
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub bet_asset: AssetInfoRaw,
+    pub oracle_addr: CanonicalAddr,
+    pub prediction_addr: CanonicalAddr,
+}
+
+/// Market which is being created, only exists until its contracts are instantiated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingMarket {
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub bet_asset: AssetInfoRaw,
+    pub public_execution: bool,
+    pub referral_fee_rate: Decimal,
+    pub params: MarketParams,
+    pub oracle_addr: Option<CanonicalAddr>,
+}
+
+pub fn store_config<S: Storage>(storage: &mut S, data: &Config) -> StdResult<()> {
+    Singleton::new(storage, KEY_CONFIG).save(data)
+}
+pub fn read_config<S: Storage>(storage: &S) -> StdResult<Config> {
+    ReadonlySingleton::new(storage, KEY_CONFIG).load()
+}
+
+pub fn store_pending_market<S: Storage>(storage: &mut S, data: &PendingMarket) -> StdResult<()> {
+    Singleton::new(storage, KEY_PENDING_MARKET).save(data)
+}
+pub fn may_read_pending_market<S: Storage>(storage: &S) -> StdResult<Option<PendingMarket>> {
+    ReadonlySingleton::new(storage, KEY_PENDING_MARKET).may_load()
+}
+pub fn remove_pending_market<S: Storage>(storage: &mut S) {
+    Singleton::<S, PendingMarket>::new(storage, KEY_PENDING_MARKET).remove()
+}
+
+pub fn store_market<S: Storage>(storage: &mut S, market_id: u64, data: &Market) -> StdResult<()> {
+    Bucket::new(PREFIX_MARKET, storage).save(&market_id.to_be_bytes(), data)
+}
+pub fn read_market<S: Storage>(storage: &S, market_id: u64) -> StdResult<Market> {
+    ReadonlyBucket::new(PREFIX_MARKET, storage).load(&market_id.to_be_bytes())
+}
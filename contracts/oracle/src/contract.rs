@@ -21,7 +21,15 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
 
     store_config(&mut deps.storage, &config)?;
 
-    Ok(InitResponse::default())
+    let mut messages = vec![];
+    if let Some(init_hook) = msg.init_hook {
+        messages.push(init_hook.into_msg()?);
+    }
+
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -3,7 +3,11 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use scrt_prediction::prediction::{ConfigResponse, HandleMsg, InitMsg, QueryMsg};
+use scrt_prediction::prediction::{
+    ClaimersResponse, ConfigResponse, FeeRecipientsResponse, HandleMsg, InitMsg,
+    LeaderboardResponse, MarketResponse, MarketsResponse, QueryMsg, ReferralStatsResponse,
+    RoundsResponse, SolvencyResponse, UserBetsResponse, UserStatsResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,4 +19,14 @@ fn main() {
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(MarketResponse), &out_dir);
+    export_schema(&schema_for!(MarketsResponse), &out_dir);
+    export_schema(&schema_for!(RoundsResponse), &out_dir);
+    export_schema(&schema_for!(UserBetsResponse), &out_dir);
+    export_schema(&schema_for!(FeeRecipientsResponse), &out_dir);
+    export_schema(&schema_for!(UserStatsResponse), &out_dir);
+    export_schema(&schema_for!(LeaderboardResponse), &out_dir);
+    export_schema(&schema_for!(ReferralStatsResponse), &out_dir);
+    export_schema(&schema_for!(ClaimersResponse), &out_dir);
+    export_schema(&schema_for!(SolvencyResponse), &out_dir);
 }
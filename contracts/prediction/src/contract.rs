@@ -1,12 +1,24 @@
 use cosmwasm_std::{
-    from_binary, to_binary, Api, Binary, Decimal, Env, Extern, HandleResult, HumanAddr,
-    InitResponse, Querier, StdError, StdResult, Storage, Uint128,
+    from_binary, to_binary, Api, Binary, Env, Extern, HandleResult, HumanAddr, InitResponse,
+    Querier, StdError, StdResult, Storage, Uint128,
 };
 
-use crate::handler::{bet, claim};
-use crate::manage::{execute_round, pause, start_genesis_round, update_config, withdraw};
-use crate::query::{query_bet, query_config, query_round, query_state};
-use crate::state::{read_config, store_config, store_state, Config, State};
+use crate::handler::{
+    add_claimer, bet, cancel_bet, claim, claim_all, claim_for, claim_many, claim_referral_rewards,
+    distribute_round, remove_claimer, switch_position,
+};
+use crate::manage::{
+    accept_operator, accept_ownership, add_market, disable_emergency, enable_emergency,
+    execute_round, fee_recipients_to_raw, migrate_bets, migrate_legacy, pause, propose_operator,
+    propose_owner, recover_rounds, register_market, start_genesis_round, update_config,
+    update_market, withdraw,
+};
+use crate::query::{
+    query_bet, query_claimers, query_config, query_fee_recipients, query_leaderboard, query_market,
+    query_markets, query_referral_stats, query_round, query_rounds, query_solvency, query_state,
+    query_user_bets, query_user_stats,
+};
+use crate::state::{read_market, store_config, Config};
 use scrt_prediction::asset::AssetInfoRaw;
 use scrt_prediction::prediction::{Cw20HookMsg, HandleMsg, InitMsg, Position, QueryMsg};
 
@@ -15,38 +27,35 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
-    if msg.fee_rate > Decimal::one() {
-        return Err(StdError::generic_err("Invalid fee rate"));
-    }
-
-    if msg.grace_interval > msg.interval {
-        return Err(StdError::generic_err("Invalid grace interval"));
-    }
-
-    let config = Config {
+    let mut config = Config {
+        contract_addr: deps.api.canonical_address(&env.contract.address)?,
         owner_addr: deps.api.canonical_address(&env.message.sender)?,
         operator_addr: deps.api.canonical_address(&msg.operator_addr)?,
-        treasury_addr: deps.api.canonical_address(&msg.treasury_addr)?,
-        bet_asset: msg.bet_asset.to_raw(deps)?,
-        oracle_addr: deps.api.canonical_address(&msg.oracle_addr)?,
-        oracle_code_hash: msg.oracle_code_hash,
-        fee_rate: msg.fee_rate,
-        interval: msg.interval,
-        grace_interval: msg.grace_interval,
+        fee_recipients: fee_recipients_to_raw(deps, msg.fee_recipients)?,
+        public_execution: msg.public_execution,
+        referral_fee_rate: msg.referral_fee_rate,
+        market_count: 0,
+        pending_owner: None,
+        pending_operator: None,
     };
+    config.validate()?;
+
+    let mut messages = vec![];
+    for market in msg.markets {
+        let (_, market_messages) = add_market(deps, &mut config, market)?;
+        messages.extend(market_messages);
+    }
 
     store_config(&mut deps.storage, &config)?;
 
-    store_state(
-        &mut deps.storage,
-        &State {
-            epoch: Uint128(0),
-            total_fee: Uint128(0),
-            paused: true,
-        },
-    )?;
+    if let Some(init_hook) = msg.init_hook {
+        messages.push(init_hook.into_msg()?);
+    }
 
-    Ok(InitResponse::default())
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -56,33 +65,100 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 ) -> HandleResult {
     match msg {
         HandleMsg::Receive { amount, msg, from } => receive_cw20(deps, env, from, amount, msg),
-        HandleMsg::Bet { position } => try_bet(deps, env, position),
+        HandleMsg::Bet {
+            market_id,
+            position,
+            referrer,
+        } => try_bet(deps, env, market_id, position, referrer),
         HandleMsg::UpdateConfig {
-            owner_addr,
-            operator_addr,
-            treasury_addr,
+            fee_recipients,
+            public_execution,
+            referral_fee_rate,
+        } => update_config(
+            deps,
+            env,
+            fee_recipients,
+            public_execution,
+            referral_fee_rate,
+        ),
+        HandleMsg::ProposeOwner { addr, expires_in } => propose_owner(deps, env, addr, expires_in),
+        HandleMsg::AcceptOwnership {} => accept_ownership(deps, env),
+        HandleMsg::ProposeOperator { addr, expires_in } => {
+            propose_operator(deps, env, addr, expires_in)
+        }
+        HandleMsg::AcceptOperator {} => accept_operator(deps, env),
+        HandleMsg::RegisterMarket { market } => register_market(deps, env, market),
+        HandleMsg::UpdateMarket {
+            market_id,
             oracle_addr,
             oracle_code_hash,
             fee_rate,
+            fee_mode,
             interval,
             grace_interval,
-        } => update_config(
+            keeper_delay,
+            keeper_bounty,
+            min_bet,
+            max_bet_per_user,
+            max_round_pool,
+            cancel_fee_rate,
+            draw_policy,
+            auto_payout,
+        } => update_market(
             deps,
             env,
-            owner_addr,
-            operator_addr,
-            treasury_addr,
+            market_id,
             oracle_addr,
             oracle_code_hash,
             fee_rate,
+            fee_mode,
             interval,
             grace_interval,
+            keeper_delay,
+            keeper_bounty,
+            min_bet,
+            max_bet_per_user,
+            max_round_pool,
+            cancel_fee_rate,
+            draw_policy,
+            auto_payout,
         ),
-        HandleMsg::Claim { epoch } => claim(deps, env, epoch),
-        HandleMsg::Withdraw {} => withdraw(deps, env),
-        HandleMsg::ExecuteRound {} => execute_round(deps, env),
-        HandleMsg::Pause {} => pause(deps, env),
-        HandleMsg::StartGenesisRound {} => start_genesis_round(deps, env),
+        HandleMsg::CancelBet { market_id } => cancel_bet(deps, env, market_id),
+        HandleMsg::SwitchPosition {
+            market_id,
+            position,
+        } => switch_position(deps, env, market_id, position),
+        HandleMsg::Claim {
+            market_id,
+            epoch,
+            recipient,
+        } => claim(deps, env, market_id, epoch, recipient),
+        HandleMsg::ClaimFor {
+            market_id,
+            user,
+            epoch,
+        } => claim_for(deps, env, market_id, user, epoch),
+        HandleMsg::AddClaimer { claimer } => add_claimer(deps, env, claimer),
+        HandleMsg::RemoveClaimer { claimer } => remove_claimer(deps, env, claimer),
+        HandleMsg::ClaimMany { market_id, epochs } => claim_many(deps, env, market_id, epochs),
+        HandleMsg::ClaimAll { market_id, limit } => claim_all(deps, env, market_id, limit),
+        HandleMsg::ClaimReferralRewards { market_id, limit } => {
+            claim_referral_rewards(deps, env, market_id, limit)
+        }
+        HandleMsg::Withdraw { market_id } => withdraw(deps, env, market_id),
+        HandleMsg::ExecuteRound { market_id } => execute_round(deps, env, market_id),
+        HandleMsg::DistributeRound {
+            market_id,
+            epoch,
+            limit,
+        } => distribute_round(deps, env, market_id, epoch, limit),
+        HandleMsg::Pause { market_id } => pause(deps, env, market_id),
+        HandleMsg::EnableEmergency { market_id } => enable_emergency(deps, env, market_id),
+        HandleMsg::DisableEmergency { market_id } => disable_emergency(deps, env, market_id),
+        HandleMsg::StartGenesisRound { market_id } => start_genesis_round(deps, env, market_id),
+        HandleMsg::RecoverRounds { market_id } => recover_rounds(deps, env, market_id),
+        HandleMsg::MigrateLegacy { limit } => migrate_legacy(deps, env, limit),
+        HandleMsg::MigrateBets { user, epochs } => migrate_bets(deps, env, user, epochs),
     }
 }
 
@@ -96,13 +172,17 @@ fn receive_cw20<S: Storage, A: Api, Q: Querier>(
 ) -> HandleResult {
     if let Some(bin_msg) = msg {
         match from_binary(&bin_msg)? {
-            Cw20HookMsg::Bet { position } => {
-                let config = read_config(&deps.storage)?;
-                match config.bet_asset {
+            Cw20HookMsg::Bet {
+                market_id,
+                position,
+                referrer,
+            } => {
+                let market = read_market(&deps.storage, market_id)?;
+                match market.bet_asset {
                     AssetInfoRaw::NativeToken { .. } => Err(StdError::generic_err("invalid asset")),
                     AssetInfoRaw::Token { contract_addr, .. } => {
                         if env.message.sender == deps.api.human_address(&contract_addr)? {
-                            bet(deps, env, from, position, amount)
+                            bet(deps, env, market_id, from, position, amount, referrer)
                         } else {
                             Err(StdError::generic_err("invalid asset"))
                         }
@@ -118,11 +198,13 @@ fn receive_cw20<S: Storage, A: Api, Q: Querier>(
 fn try_bet<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    market_id: u64,
     position: Position,
+    referrer: Option<HumanAddr>,
 ) -> HandleResult {
-    let config = read_config(&deps.storage)?;
+    let market = read_market(&deps.storage, market_id)?;
 
-    match config.bet_asset {
+    match market.bet_asset {
         AssetInfoRaw::NativeToken { denom } => {
             let amount: Uint128 = env
                 .message
@@ -131,7 +213,15 @@ fn try_bet<S: Storage, A: Api, Q: Querier>(
                 .find(|c| c.denom == denom)
                 .map(|c| Uint128::from(c.amount))
                 .unwrap_or_else(Uint128::zero);
-            bet(deps, env.clone(), env.message.sender, position, amount)
+            bet(
+                deps,
+                env.clone(),
+                market_id,
+                env.message.sender,
+                position,
+                amount,
+                referrer,
+            )
         }
         AssetInfoRaw::Token { .. } => Err(StdError::generic_err("invalid asset")),
     }
@@ -143,8 +233,53 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        QueryMsg::State {} => to_binary(&query_state(deps)?),
-        QueryMsg::Round { epoch } => to_binary(&query_round(deps, epoch)?),
-        QueryMsg::Bet { epoch, user } => to_binary(&query_bet(deps, epoch, user)?),
+        QueryMsg::Market { market_id } => to_binary(&query_market(deps, market_id)?),
+        QueryMsg::Markets { start_after, limit } => {
+            to_binary(&query_markets(deps, start_after, limit)?)
+        }
+        QueryMsg::State { market_id } => to_binary(&query_state(deps, market_id)?),
+        QueryMsg::Round { market_id, epoch } => to_binary(&query_round(deps, market_id, epoch)?),
+        QueryMsg::Rounds {
+            market_id,
+            start_after,
+            limit,
+            order,
+        } => to_binary(&query_rounds(deps, market_id, start_after, limit, order)?),
+        QueryMsg::Bet {
+            market_id,
+            epoch,
+            user,
+        } => to_binary(&query_bet(deps, market_id, epoch, user)?),
+        QueryMsg::UserBets {
+            market_id,
+            user,
+            start_after,
+            limit,
+            order,
+        } => to_binary(&query_user_bets(
+            deps,
+            market_id,
+            user,
+            start_after,
+            limit,
+            order,
+        )?),
+        QueryMsg::FeeRecipients { market_id } => to_binary(&query_fee_recipients(deps, market_id)?),
+        QueryMsg::UserStats { market_id, user } => {
+            to_binary(&query_user_stats(deps, market_id, user)?)
+        }
+        QueryMsg::Leaderboard {
+            market_id,
+            metric,
+            limit,
+        } => to_binary(&query_leaderboard(deps, market_id, metric, limit)?),
+        QueryMsg::ReferralStats {
+            market_id,
+            referrer,
+        } => to_binary(&query_referral_stats(deps, market_id, referrer)?),
+        QueryMsg::Solvency { start_after, limit } => {
+            to_binary(&query_solvency(deps, start_after, limit)?)
+        }
+        QueryMsg::Claimers { user } => to_binary(&query_claimers(deps, user)?),
     }
 }
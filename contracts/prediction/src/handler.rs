@@ -1,65 +1,288 @@
 use cosmwasm_std::{
-    log, Api, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier, StdError, Storage,
-    Uint128,
+    Api, CanonicalAddr, CosmosMsg, Env, Extern, HandleResponse, HandleResult, HumanAddr,
+    LogAttribute, Querier, StdError, StdResult, Storage, Uint128,
 };
 
+use crate::math::decimal_mul;
 use crate::state::{
-    read_bet, read_config, read_round, read_state, store_bet, store_round, Bet, Config, Round,
+    add_referral_bet, may_read_bet, read_bet, read_claim_index, read_claimers, read_config,
+    read_distributed_count, read_market, read_referral, read_referral_rounds, read_referrer,
+    read_round, read_round_bet_count, read_round_bettor, read_state, read_user_bet_count,
+    read_user_bet_epoch, read_user_stats, store_bet, store_claim_index, store_claimers,
+    store_distributed_count, store_referral, store_referrer, store_round, store_state,
+    store_user_stats, sub_referral_bet, update_leaderboard, Bet, Config, Market, Referral, Round,
     State,
 };
 use scrt_prediction::asset::Asset;
-use scrt_prediction::prediction::Position;
+use scrt_prediction::event::{
+    BetEvent, CancelBetEvent, ClaimEvent, ClaimReferralRewardsEvent, ClaimerEvent,
+    DistributeRoundEvent, Event, FeeAccrualEvent, FeeSource, SwitchPositionEvent,
+};
+use scrt_prediction::prediction::{BetResult, LeaderboardMetric, Position};
+
+const DEFAULT_CLAIM_LIMIT: u32 = 10;
+const MAX_CLAIM_LIMIT: u32 = 30;
+const MAX_STATS_UPDATE: u64 = 30;
+const MAX_CLAIMERS: usize = 10;
 
 pub fn bet<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    market_id: u64,
     user: HumanAddr,
     position: Position,
     amount: Uint128,
+    referrer: Option<HumanAddr>,
 ) -> HandleResult {
-    let state: State = read_state(&deps.storage)?;
-    let mut round: Round = read_round(&deps.storage, state.epoch)?;
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    let mut round: Round = read_round(&deps.storage, market_id, state.epoch)?;
 
     if round.bettable(env) == false {
         return Err(StdError::generic_err("Cannot bet"));
     }
 
-    let user_bet = read_bet(
-        &deps.storage,
-        state.epoch,
-        deps.api.canonical_address(&user)?,
-    );
+    if !market.valid_position(&position) || !round.valid_position(&position) {
+        return Err(StdError::generic_err("Invalid position"));
+    }
 
-    if user_bet.is_ok() {
-        return Err(StdError::generic_err("Already bet"));
+    if amount.is_zero() || amount < market.min_bet {
+        return Err(StdError::generic_err("Bet amount is too small"));
     }
 
-    round.total_amount = round.total_amount + amount;
+    let user_raw = deps.api.canonical_address(&user)?;
 
-    if position == Position::UP {
-        round.up_amount = round.up_amount + amount;
-    } else if position == Position::DOWN {
-        round.down_amount = round.down_amount + amount;
-    } else {
-        return Err(StdError::generic_err("Cannot bet DRAW"));
+    // user can add to existing bet of same position until round is locked
+    let mut user_bet = match may_read_bet(&deps.storage, market_id, state.epoch, user_raw.clone())?
+    {
+        Some(user_bet) => {
+            if user_bet.position != position && !user_bet.amount.is_zero() {
+                return Err(StdError::generic_err("Cannot switch position"));
+            }
+            Bet {
+                position: position.clone(),
+                ..user_bet
+            }
+        }
+        None => Bet {
+            amount: Uint128(0),
+            position: position.clone(),
+            claimed: false,
+        },
+    };
+    user_bet.amount = user_bet.amount + amount;
+
+    if !market.max_bet_per_user.is_zero() && user_bet.amount > market.max_bet_per_user {
+        return Err(StdError::generic_err("Bet amount is too large"));
+    }
+
+    if !market.max_round_pool.is_zero() && round.total_amount + amount > market.max_round_pool {
+        return Err(StdError::generic_err("Round pool is full"));
     }
 
-    store_round(&mut deps.storage, state.epoch, &round)?;
+    round.add_bet_amount(&position, amount);
+    state.open_pool = state.open_pool + amount;
+
+    // referrer is stored on first use and cannot be changed
+    let referrer = match read_referrer(&deps.storage, &user_raw)? {
+        Some(referrer) => Some(referrer),
+        None => match referrer {
+            Some(referrer) => {
+                let referrer = deps.api.canonical_address(&referrer)?;
+                if referrer == user_raw {
+                    return Err(StdError::generic_err("Invalid referrer"));
+                }
+                store_referrer(&mut deps.storage, &user_raw, &referrer)?;
+                Some(referrer)
+            }
+            None => None,
+        },
+    };
+    if let Some(referrer) = &referrer {
+        round.referred_amount = round.referred_amount + amount;
+        add_referral_bet(&mut deps.storage, market_id, state.epoch, referrer, amount)?;
+    }
 
+    store_round(&mut deps.storage, market_id, state.epoch, &round)?;
     store_bet(
         &mut deps.storage,
+        market_id,
         state.epoch,
-        deps.api.canonical_address(&user)?,
-        &Bet {
-            amount,
-            position,
-            claimed: false,
-        },
+        user_raw,
+        &user_bet,
     )?;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    let referrer = match referrer {
+        Some(referrer) => Some(deps.api.human_address(&referrer)?),
+        None => None,
+    };
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![log("action", "bet"), log("amount", amount)],
+        log: BetEvent {
+            market_id,
+            epoch: state.epoch,
+            user,
+            position,
+            amount,
+            bet_amount: user_bet.amount,
+            referrer,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn cancel_bet<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+) -> HandleResult {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    let mut round: Round = read_round(&deps.storage, market_id, state.epoch)?;
+
+    if !round.bettable(env.clone()) {
+        return Err(StdError::generic_err("Cannot cancel bet"));
+    }
+
+    let user = deps.api.canonical_address(&env.message.sender)?;
+    let mut user_bet = read_bet(&deps.storage, market_id, state.epoch, user.clone())?;
+    if user_bet.amount.is_zero() {
+        return Err(StdError::generic_err("Nothing to cancel"));
+    }
+
+    let penalty = decimal_mul(user_bet.amount, market.cancel_fee_rate);
+    let refund_amount = (user_bet.amount - penalty)?;
+
+    round.sub_bet_amount(&user_bet.position, user_bet.amount)?;
+    state.total_fee = state.total_fee + penalty;
+    state.open_pool = (state.open_pool - user_bet.amount)?;
+
+    if let Some(referrer) = read_referrer(&deps.storage, &user)? {
+        let referred_amount = sub_referral_bet(
+            &mut deps.storage,
+            market_id,
+            state.epoch,
+            &referrer,
+            user_bet.amount,
+        )?;
+        round.referred_amount = (round.referred_amount - referred_amount)?;
+    }
+
+    // bet is kept with zero amount, so user can bet again in this round
+    user_bet.amount = Uint128(0);
+
+    store_round(&mut deps.storage, market_id, state.epoch, &round)?;
+    store_bet(&mut deps.storage, market_id, state.epoch, user, &user_bet)?;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    let mut messages = vec![];
+    if !refund_amount.is_zero() {
+        let return_asset = Asset {
+            amount: refund_amount,
+            info: market.bet_asset.to_normal(deps)?,
+        };
+        messages
+            .push(return_asset.into_msg(env.contract.address.clone(), env.message.sender.clone())?);
+    }
+
+    let mut logs = CancelBetEvent {
+        market_id,
+        epoch: state.epoch,
+        user: env.message.sender,
+        amount: refund_amount,
+        penalty,
+    }
+    .to_logs();
+    logs.extend(fee_accrual_logs(
+        market_id,
+        state.epoch,
+        FeeSource::CancelPenalty,
+        penalty,
+        state.total_fee,
+    ));
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn switch_position<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    position: Position,
+) -> HandleResult {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    let mut round: Round = read_round(&deps.storage, market_id, state.epoch)?;
+
+    if !round.bettable(env.clone()) {
+        return Err(StdError::generic_err("Cannot switch position"));
+    }
+
+    if !market.valid_position(&position) || !round.valid_position(&position) {
+        return Err(StdError::generic_err("Invalid position"));
+    }
+
+    let user = deps.api.canonical_address(&env.message.sender)?;
+    let mut user_bet = read_bet(&deps.storage, market_id, state.epoch, user.clone())?;
+    if user_bet.amount.is_zero() {
+        return Err(StdError::generic_err("Nothing to switch"));
+    }
+
+    if user_bet.position == position {
+        return Err(StdError::generic_err("Same position"));
+    }
+
+    let penalty = decimal_mul(user_bet.amount, market.cancel_fee_rate);
+
+    round.sub_bet_amount(&user_bet.position, user_bet.amount)?;
+    user_bet.amount = (user_bet.amount - penalty)?;
+    user_bet.position = position;
+    round.add_bet_amount(&user_bet.position, user_bet.amount);
+    state.total_fee = state.total_fee + penalty;
+    state.open_pool = (state.open_pool - penalty)?;
+
+    if let Some(referrer) = read_referrer(&deps.storage, &user)? {
+        let referred_amount = sub_referral_bet(
+            &mut deps.storage,
+            market_id,
+            state.epoch,
+            &referrer,
+            penalty,
+        )?;
+        round.referred_amount = (round.referred_amount - referred_amount)?;
+    }
+
+    store_round(&mut deps.storage, market_id, state.epoch, &round)?;
+    store_bet(&mut deps.storage, market_id, state.epoch, user, &user_bet)?;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    let mut logs = SwitchPositionEvent {
+        market_id,
+        epoch: state.epoch,
+        user: env.message.sender,
+        position: user_bet.position,
+        amount: user_bet.amount,
+        penalty,
+    }
+    .to_logs();
+    logs.extend(fee_accrual_logs(
+        market_id,
+        state.epoch,
+        FeeSource::SwitchPenalty,
+        penalty,
+        state.total_fee,
+    ));
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: logs,
         data: None,
     })
 }
@@ -67,50 +290,619 @@ pub fn bet<S: Storage, A: Api, Q: Querier>(
 pub fn claim<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    market_id: u64,
+    epoch: Uint128,
+    recipient: Option<HumanAddr>,
+) -> HandleResult {
+    let user = env.message.sender.clone();
+    let recipient = recipient.unwrap_or_else(|| user.clone());
+
+    claim_to(deps, env, market_id, epoch, user, recipient)
+}
+
+pub fn claim_for<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    user: HumanAddr,
+    epoch: Uint128,
+) -> HandleResult {
+    // permission check
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    let user_raw = deps.api.canonical_address(&user)?;
+    if sender != user_raw && !read_claimers(&deps.storage, &user_raw)?.contains(&sender) {
+        return Err(StdError::unauthorized());
+    }
+
+    claim_to(deps, env, market_id, epoch, user.clone(), user)
+}
+
+fn claim_to<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    epoch: Uint128,
+    user: HumanAddr,
+    recipient: HumanAddr,
+) -> HandleResult {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let user_raw = deps.api.canonical_address(&user)?;
+    update_user_stats(&mut deps.storage, market_id, &user_raw)?;
+
+    let (claim_amount, fee_logs) = claim_bet(
+        &mut deps.storage,
+        env.clone(),
+        market_id,
+        &market,
+        epoch,
+        &user_raw,
+    )?;
+
+    if claim_amount.is_zero() {
+        return Err(StdError::generic_err("Nothing to claim"));
+    }
+
+    let return_asset = Asset {
+        amount: claim_amount,
+        info: market.bet_asset.to_normal(deps)?,
+    };
+
+    let mut logs = ClaimEvent {
+        market_id,
+        epoch,
+        user,
+        recipient: recipient.clone(),
+        amount: claim_amount,
+    }
+    .to_logs();
+    logs.extend(fee_logs);
+
+    Ok(HandleResponse {
+        messages: vec![return_asset.into_msg(env.contract.address, recipient)?],
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn add_claimer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    claimer: HumanAddr,
+) -> HandleResult {
+    let user = deps.api.canonical_address(&env.message.sender)?;
+    let claimer_raw = deps.api.canonical_address(&claimer)?;
+
+    let mut claimers = read_claimers(&deps.storage, &user)?;
+    if claimers.contains(&claimer_raw) {
+        return Err(StdError::generic_err("Claimer already added"));
+    }
+    if claimers.len() >= MAX_CLAIMERS {
+        return Err(StdError::generic_err("Too many claimers"));
+    }
+
+    claimers.push(claimer_raw);
+    store_claimers(&mut deps.storage, &user, &claimers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: ClaimerEvent {
+            user: env.message.sender,
+            claimer,
+            allowed: true,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn remove_claimer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    claimer: HumanAddr,
+) -> HandleResult {
+    let user = deps.api.canonical_address(&env.message.sender)?;
+    let claimer_raw = deps.api.canonical_address(&claimer)?;
+
+    let mut claimers = read_claimers(&deps.storage, &user)?;
+    if !claimers.contains(&claimer_raw) {
+        return Err(StdError::generic_err("Claimer not found"));
+    }
+
+    claimers.retain(|addr| *addr != claimer_raw);
+    store_claimers(&mut deps.storage, &user, &claimers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: ClaimerEvent {
+            user: env.message.sender,
+            claimer,
+            allowed: false,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn claim_many<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    epochs: Vec<Uint128>,
+) -> HandleResult {
+    if epochs.len() > MAX_CLAIM_LIMIT as usize {
+        return Err(StdError::generic_err("Too many epochs"));
+    }
+
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let user = deps.api.canonical_address(&env.message.sender)?;
+    update_user_stats(&mut deps.storage, market_id, &user)?;
+
+    let mut logs = vec![];
+    let mut total_amount = Uint128(0);
+    for epoch in epochs {
+        let (claim_amount, fee_logs) = claim_bet(
+            &mut deps.storage,
+            env.clone(),
+            market_id,
+            &market,
+            epoch,
+            &user,
+        )?;
+        total_amount = total_amount + claim_amount;
+        logs.extend(
+            ClaimEvent {
+                market_id,
+                epoch,
+                user: env.message.sender.clone(),
+                recipient: env.message.sender.clone(),
+                amount: claim_amount,
+            }
+            .to_logs(),
+        );
+        logs.extend(fee_logs);
+    }
+
+    transfer_claimed(deps, env, market, total_amount, logs)
+}
+
+pub fn claim_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    limit: Option<u32>,
+) -> HandleResult {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let user = deps.api.canonical_address(&env.message.sender)?;
+    update_user_stats(&mut deps.storage, market_id, &user)?;
+    let limit = limit.unwrap_or(DEFAULT_CLAIM_LIMIT).min(MAX_CLAIM_LIMIT);
+
+    let mut logs = vec![];
+    let mut total_amount = Uint128(0);
+
+    // scan at most `limit` bets from the first bet which may have something to claim
+    let start_index = read_claim_index(&deps.storage, market_id, &user)?;
+    let bet_count = read_user_bet_count(&deps.storage, market_id, &user)?;
+    let last_index = bet_count.min(start_index + limit as u64);
+    let mut index = start_index;
+    while index < last_index {
+        let epoch = read_user_bet_epoch(&deps.storage, market_id, &user, index)?;
+        let round: Round = read_round(&deps.storage, market_id, epoch)?;
+        // bets of later rounds are not closed either
+        if !round.claimable(env.clone()) && !round.refundable(env.clone(), market.grace_interval) {
+            break;
+        }
+        index += 1;
+
+        let user_bet = read_bet(&deps.storage, market_id, epoch, user.clone())?;
+        if user_bet.claimed
+            || round
+                .claimable_amount(env.clone(), user_bet, market.grace_interval)
+                .is_zero()
+        {
+            continue;
+        }
+
+        let (claim_amount, fee_logs) = claim_bet(
+            &mut deps.storage,
+            env.clone(),
+            market_id,
+            &market,
+            epoch,
+            &user,
+        )?;
+        total_amount = total_amount + claim_amount;
+        logs.extend(
+            ClaimEvent {
+                market_id,
+                epoch,
+                user: env.message.sender.clone(),
+                recipient: env.message.sender.clone(),
+                amount: claim_amount,
+            }
+            .to_logs(),
+        );
+        logs.extend(fee_logs);
+    }
+
+    if index == start_index {
+        return Err(StdError::generic_err("Nothing to claim"));
+    }
+    store_claim_index(&mut deps.storage, market_id, &user, index)?;
+
+    // only bets without rewards are skipped
+    if total_amount.is_zero() {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: logs,
+            data: None,
+        });
+    }
+
+    transfer_claimed(deps, env, market, total_amount, logs)
+}
+
+pub fn claim_referral_rewards<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    limit: Option<u32>,
+) -> HandleResult {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let referrer = deps.api.canonical_address(&env.message.sender)?;
+    let limit = limit.unwrap_or(DEFAULT_CLAIM_LIMIT).min(MAX_CLAIM_LIMIT) as u64;
+
+    let mut referral: Referral = read_referral(&deps.storage, market_id, &referrer)?;
+    let rounds = read_referral_rounds(&deps.storage, market_id, &referrer, &referral, limit)?;
+
+    // rounds without rewards are skipped as claimed as well
+    if rounds.is_empty() {
+        return Err(StdError::generic_err("Nothing to claim"));
+    }
+
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    let mut fee_logs = vec![];
+    let round_count = rounds.len() as u64;
+    let mut rewards = Uint128(0);
+    let mut dust = Uint128(0);
+    for (epoch, mut round, referred_amount) in rounds {
+        if referred_amount.is_zero() {
+            continue;
+        }
+
+        let amount = round.referral_reward_of(referred_amount);
+        // dust left by rounding once all referrers of round are paid is moved to fee
+        let round_dust = round.record_referral_payout(referred_amount, amount)?;
+        store_round(&mut deps.storage, market_id, epoch, &round)?;
+        rewards = rewards + amount;
+        dust = dust + round_dust;
+        state.total_fee = state.total_fee + round_dust;
+        fee_logs.extend(fee_accrual_logs(
+            market_id,
+            epoch,
+            FeeSource::ReferralDust,
+            round_dust,
+            state.total_fee,
+        ));
+    }
+
+    referral.claimed_epoch_count += round_count;
+    referral.claimed_amount = referral.claimed_amount + rewards;
+    store_referral(&mut deps.storage, market_id, &referrer, &referral)?;
+
+    state.referral_rewards = (state.referral_rewards - (rewards + dust))?;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    let mut messages = vec![];
+    if !rewards.is_zero() {
+        let return_asset = Asset {
+            amount: rewards,
+            info: market.bet_asset.to_normal(deps)?,
+        };
+        messages.push(return_asset.into_msg(env.contract.address, env.message.sender.clone())?);
+    }
+
+    let mut logs = ClaimReferralRewardsEvent {
+        market_id,
+        referrer: env.message.sender,
+        amount: rewards,
+    }
+    .to_logs();
+    logs.extend(fee_logs);
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn distribute_round<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
     epoch: Uint128,
+    limit: Option<u32>,
 ) -> HandleResult {
     let config: Config = read_config(&deps.storage)?;
-    let round: Round = read_round(&deps.storage, epoch)?;
+    let market: Market = read_market(&deps.storage, market_id)?;
+
+    // permission check
+    if !config.public_execution
+        && deps.api.canonical_address(&env.message.sender)? != config.operator_addr
+    {
+        return Err(StdError::unauthorized());
+    }
+
+    if !market.auto_payout {
+        return Err(StdError::generic_err("Auto payout is disabled"));
+    }
+
+    let bettor_count = read_round_bet_count(&deps.storage, market_id, epoch)?;
+    if read_distributed_count(&deps.storage, market_id, epoch)? >= bettor_count {
+        return Err(StdError::generic_err("Nothing to distribute"));
+    }
 
-    if !round.claimable(env.clone()) && !round.refundable(env.clone(), config.grace_interval) {
+    let (messages, logs) = distribute_bets(deps, env, market_id, &market, epoch, limit)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: None,
+    })
+}
+
+/// Send claimable amounts to unclaimed bettors of closed round, up to limit bettors
+/// from the stored cursor, and mark their bets as claimed
+pub fn distribute_bets<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    market: &Market,
+    epoch: Uint128,
+    limit: Option<u32>,
+) -> StdResult<(Vec<CosmosMsg>, Vec<LogAttribute>)> {
+    let mut round: Round = read_round(&deps.storage, market_id, epoch)?;
+    if !round.claimable(env.clone()) && !round.refundable(env.clone(), market.grace_interval) {
         return Err(StdError::generic_err("Round is not closed"));
     }
 
-    let mut user_bet = read_bet(
-        &deps.storage,
+    let limit = limit.unwrap_or(DEFAULT_CLAIM_LIMIT).min(MAX_CLAIM_LIMIT) as u64;
+    let bettor_count = read_round_bet_count(&deps.storage, market_id, epoch)?;
+    let distributed_count = read_distributed_count(&deps.storage, market_id, epoch)?;
+    let last_index = bettor_count.min(distributed_count + limit);
+
+    let mut messages = vec![];
+    let mut logs = vec![];
+    let mut paid_amount = Uint128(0);
+    let mut dust = Uint128(0);
+    for index in distributed_count..last_index {
+        let user = read_round_bettor(&deps.storage, market_id, epoch, index)?;
+        let mut user_bet = read_bet(&deps.storage, market_id, epoch, user.clone())?;
+        if user_bet.claimed {
+            continue;
+        }
+
+        user_bet.claimed = true;
+        store_bet(&mut deps.storage, market_id, epoch, user.clone(), &user_bet)?;
+        update_user_stats(&mut deps.storage, market_id, &user)?;
+
+        let amount = round.claimable_amount(env.clone(), user_bet.clone(), market.grace_interval);
+        paid_amount = paid_amount + amount;
+        dust = dust + round.record_payout(&user_bet, amount)?;
+        if amount.is_zero() {
+            continue;
+        }
+
+        let user = deps.api.human_address(&user)?;
+        let return_asset = Asset {
+            amount,
+            info: market.bet_asset.to_normal(deps)?,
+        };
+        messages.push(return_asset.into_msg(env.contract.address.clone(), user.clone())?);
+        logs.extend(
+            ClaimEvent {
+                market_id,
+                epoch,
+                user: user.clone(),
+                recipient: user,
+                amount,
+            }
+            .to_logs(),
+        );
+    }
+    store_distributed_count(&mut deps.storage, market_id, epoch, last_index)?;
+    store_round(&mut deps.storage, market_id, epoch, &round)?;
+    logs.extend(record_paid(
+        &mut deps.storage,
+        market_id,
         epoch,
-        deps.api.canonical_address(&env.message.sender)?,
-    )?;
+        round.settled(),
+        paid_amount,
+        dust,
+    )?);
+
+    logs.extend(
+        DistributeRoundEvent {
+            market_id,
+            epoch,
+            distributed_count: last_index,
+            bettor_count,
+        }
+        .to_logs(),
+    );
+
+    Ok((messages, logs))
+}
+
+/// Count results of user bets in settled rounds, up to limit bets each time,
+/// and rank changed values in leaderboards
+pub fn update_user_stats<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    user: &CanonicalAddr,
+) -> StdResult<()> {
+    let previous_stats = read_user_stats(storage, market_id, user)?;
+    let mut stats = previous_stats.clone();
+    let bet_count = read_user_bet_count(storage, market_id, user)?;
+    let last_index = bet_count.min(stats.settled_bet_count + MAX_STATS_UPDATE);
+
+    while stats.settled_bet_count < last_index {
+        let epoch = read_user_bet_epoch(storage, market_id, user, stats.settled_bet_count)?;
+        let round: Round = read_round(storage, market_id, epoch)?;
+        if !round.settled() {
+            break;
+        }
+
+        // cancelled bets keep position with zero amount
+        let user_bet = read_bet(storage, market_id, epoch, user.clone())?;
+        match round.bet_result(&user_bet) {
+            BetResult::Won if !user_bet.amount.is_zero() => {
+                stats.rounds_played += 1;
+                stats.total_wagered = stats.total_wagered + user_bet.amount;
+                stats.total_won = stats.total_won + round.reward_of(&user_bet);
+                stats.current_streak += 1;
+                stats.best_streak = stats.best_streak.max(stats.current_streak);
+            }
+            BetResult::Lost if !user_bet.amount.is_zero() => {
+                stats.rounds_played += 1;
+                stats.total_wagered = stats.total_wagered + user_bet.amount;
+                stats.current_streak = 0;
+            }
+            _ => {}
+        }
+        stats.settled_bet_count += 1;
+    }
+
+    if stats.settled_bet_count == previous_stats.settled_bet_count {
+        return Ok(());
+    }
+
+    store_user_stats(storage, market_id, user, &stats)?;
+    for metric in [
+        LeaderboardMetric::RoundsPlayed,
+        LeaderboardMetric::TotalWagered,
+        LeaderboardMetric::TotalWon,
+        LeaderboardMetric::NetProfit,
+        LeaderboardMetric::BestStreak,
+    ]
+    .iter()
+    {
+        let value = stats.metric_value(metric);
+        if value != previous_stats.metric_value(metric) {
+            update_leaderboard(storage, market_id, metric, user, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Mark user bet as claimed and return claimable amount with logs of fee accrued from dust
+fn claim_bet<S: Storage>(
+    storage: &mut S,
+    env: Env,
+    market_id: u64,
+    market: &Market,
+    epoch: Uint128,
+    user: &CanonicalAddr,
+) -> StdResult<(Uint128, Vec<LogAttribute>)> {
+    let mut round: Round = read_round(storage, market_id, epoch)?;
+
+    if !round.claimable(env.clone()) && !round.refundable(env.clone(), market.grace_interval) {
+        return Err(StdError::generic_err("Round is not closed"));
+    }
+
+    let mut user_bet = read_bet(storage, market_id, epoch, user.clone())?;
 
     if user_bet.claimed {
         return Err(StdError::generic_err("Already claimed"));
     }
 
     user_bet.claimed = true;
-    store_bet(
-        &mut deps.storage,
+    store_bet(storage, market_id, epoch, user.clone(), &user_bet)?;
+
+    let amount = round.claimable_amount(env, user_bet.clone(), market.grace_interval);
+    let dust = round.record_payout(&user_bet, amount)?;
+    store_round(storage, market_id, epoch, &round)?;
+    let fee_logs = record_paid(storage, market_id, epoch, round.settled(), amount, dust)?;
+
+    Ok((amount, fee_logs))
+}
+
+/// Deduct paid amount from liabilities of market, and move reward dust of round to fee.
+/// Returns logs of fee accrued from dust
+pub fn record_paid<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    settled: bool,
+    amount: Uint128,
+    dust: Uint128,
+) -> StdResult<Vec<LogAttribute>> {
+    if amount.is_zero() && dust.is_zero() {
+        return Ok(vec![]);
+    }
+
+    let mut state: State = read_state(storage, market_id)?;
+    if settled {
+        state.unclaimed_amount = (state.unclaimed_amount - (amount + dust))?;
+    } else {
+        // bets of expired round which is not cancelled yet
+        state.open_pool = (state.open_pool - amount)?;
+    }
+    state.total_fee = state.total_fee + dust;
+    store_state(storage, market_id, &state)?;
+
+    Ok(fee_accrual_logs(
+        market_id,
         epoch,
-        deps.api.canonical_address(&env.message.sender)?,
-        &user_bet,
-    )?;
-    let claim_amount = round.claimable_amount(env.clone(), user_bet, config.grace_interval);
+        FeeSource::RewardDust,
+        dust,
+        state.total_fee,
+    ))
+}
 
-    if claim_amount.is_zero() {
+/// Logs of fee added to or deducted from accrued fee of market, none for zero fee
+pub fn fee_accrual_logs(
+    market_id: u64,
+    epoch: Uint128,
+    source: FeeSource,
+    fee: Uint128,
+    total_fee: Uint128,
+) -> Vec<LogAttribute> {
+    if fee.is_zero() {
+        return vec![];
+    }
+
+    FeeAccrualEvent {
+        market_id,
+        epoch,
+        source,
+        fee,
+        referral_fee: Uint128(0),
+        total_fee,
+    }
+    .to_logs()
+}
+
+fn transfer_claimed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market: Market,
+    total_amount: Uint128,
+    logs: Vec<LogAttribute>,
+) -> HandleResult {
+    if total_amount.is_zero() {
         return Err(StdError::generic_err("Nothing to claim"));
     }
 
     let return_asset = Asset {
-        amount: claim_amount,
-        info: config.bet_asset.to_normal(deps)?,
+        amount: total_amount,
+        info: market.bet_asset.to_normal(deps)?,
     };
 
     Ok(HandleResponse {
         messages: vec![return_asset.into_msg(env.contract.address, env.message.sender)?],
-        log: vec![
-            log("action", "claim"),
-            log("epoch", epoch),
-            log("amount", claim_amount),
-        ],
+        log: logs,
         data: None,
     })
 }
@@ -1,6 +1,7 @@
 pub mod contract;
 mod handler;
 mod manage;
+mod math;
 mod query;
 mod state;
 
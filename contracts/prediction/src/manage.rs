@@ -1,26 +1,87 @@
 use cosmwasm_std::{
-    log, Api, Decimal, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier, StdError,
-    Storage, Uint128,
+    Api, CosmosMsg, Decimal, Env, Extern, HandleResponse, HandleResult, HumanAddr, LogAttribute,
+    Querier, StdError, StdResult, Storage, Uint128,
 };
 
+use secret_toolkit::snip20::set_viewing_key_msg;
+
+use crate::handler::{distribute_bets, fee_accrual_logs, record_paid};
+use crate::math::{decimal_mul, multiply_ratio};
 use crate::query::query_price;
 use crate::state::{
-    read_config, read_round, read_state, store_config, store_round, store_state, Config, Round,
-    State,
+    count_user_bets_before, may_read_round, read_claim_index, read_config, read_legacy_bet,
+    read_legacy_config, read_legacy_migration, read_legacy_round, read_legacy_state, read_market,
+    read_round, read_state, read_user_stats, read_viewing_key, remove_legacy_bet,
+    remove_legacy_migration, remove_legacy_round, remove_legacy_state, store_bet,
+    store_claim_index, store_config, store_legacy_migration, store_market, store_round,
+    store_state, store_user_stats, store_viewing_key, Config, FeeRecipient, LegacyConfig,
+    LegacyMigration, Market, Round, State, LEGACY_MARKET_ID,
+};
+use scrt_prediction::asset::{Asset, AssetInfoRaw};
+use scrt_prediction::event::{
+    AcceptOperatorEvent, AcceptOwnershipEvent, EmergencyEvent, Event, FeeAccrualEvent, FeeSource,
+    MigrateBetsEvent, MigrateLegacyEvent, PauseEvent, ProposeOperatorEvent, ProposeOwnerEvent,
+    RegisterMarketEvent, RoundCancelEvent, RoundCloseEvent, RoundLockEvent, RoundStartEvent,
+    UpdateConfigEvent, UpdateMarketEvent, WithdrawEvent,
 };
-use scrt_prediction::asset::Asset;
+use scrt_prediction::ownership::{accept_transfer, PendingTransfer};
+use scrt_prediction::prediction::{DrawPolicy, FeeMode, FeeRecipientInfo, MarketInfo};
+
+/// Block size to pad messages to token contract
+pub const BLOCK_SIZE: usize = 256;
+/// Minimum duration of emergency mode before it can be disabled
+const EMERGENCY_DURATION: u64 = 7 * 24 * 60 * 60;
+const DEFAULT_MIGRATE_LIMIT: u32 = 30;
+const MAX_MIGRATE_LIMIT: u32 = 100;
+const MAX_MIGRATE_BETS: usize = 30;
 
 pub fn update_config<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    owner_addr: Option<HumanAddr>,
-    operator_addr: Option<HumanAddr>,
-    treasury_addr: Option<HumanAddr>,
-    oracle_addr: Option<HumanAddr>,
-    oracle_code_hash: Option<String>,
-    fee_rate: Option<Decimal>,
-    interval: Option<u64>,
-    grace_interval: Option<u64>,
+    fee_recipients: Option<Vec<FeeRecipientInfo>>,
+    public_execution: Option<bool>,
+    referral_fee_rate: Option<Decimal>,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    if let Some(fee_recipients) = fee_recipients {
+        config.fee_recipients = fee_recipients_to_raw(deps, fee_recipients)?;
+    }
+
+    if let Some(public_execution) = public_execution {
+        config.public_execution = public_execution;
+    }
+
+    if let Some(referral_fee_rate) = referral_fee_rate {
+        config.referral_fee_rate = referral_fee_rate;
+    }
+
+    config.validate()?;
+
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: UpdateConfigEvent {
+            owner_addr: deps.api.human_address(&config.owner_addr)?,
+            operator_addr: deps.api.human_address(&config.operator_addr)?,
+            public_execution: config.public_execution,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn propose_owner<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    addr: HumanAddr,
+    expires_in: Option<u64>,
 ) -> HandleResult {
     let mut config: Config = read_config(&deps.storage)?;
 
@@ -29,46 +90,291 @@ pub fn update_config<S: Storage, A: Api, Q: Querier>(
         return Err(StdError::unauthorized());
     }
 
-    if let Some(owner_addr) = owner_addr {
-        config.owner_addr = deps.api.canonical_address(&owner_addr)?;
+    let pending_owner = PendingTransfer::new(&env, deps.api.canonical_address(&addr)?, expires_in)?;
+    let expires_at = pending_owner.expires_at;
+    config.pending_owner = Some(pending_owner);
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: ProposeOwnerEvent { addr, expires_at }.to_logs(),
+        data: None,
+    })
+}
+
+pub fn accept_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    config.owner_addr = accept_transfer(config.pending_owner.take(), &env, sender_raw)?;
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: AcceptOwnershipEvent {
+            owner_addr: env.message.sender,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn propose_operator<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    addr: HumanAddr,
+    expires_in: Option<u64>,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    let pending_operator =
+        PendingTransfer::new(&env, deps.api.canonical_address(&addr)?, expires_in)?;
+    let expires_at = pending_operator.expires_at;
+    config.pending_operator = Some(pending_operator);
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: ProposeOperatorEvent { addr, expires_at }.to_logs(),
+        data: None,
+    })
+}
+
+pub fn accept_operator<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    config.operator_addr = accept_transfer(config.pending_operator.take(), &env, sender_raw)?;
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: AcceptOperatorEvent {
+            operator_addr: env.message.sender,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn register_market<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market: MarketInfo,
+) -> HandleResult {
+    let mut config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
     }
 
-    if let Some(operator_addr) = operator_addr {
-        config.operator_addr = deps.api.canonical_address(&operator_addr)?;
+    let (market_id, messages) = add_market(deps, &mut config, market)?;
+    store_config(&mut deps.storage, &config)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: RegisterMarketEvent { market_id }.to_logs(),
+        data: None,
+    })
+}
+
+pub fn fee_recipients_to_raw<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    fee_recipients: Vec<FeeRecipientInfo>,
+) -> StdResult<Vec<FeeRecipient>> {
+    fee_recipients
+        .into_iter()
+        .map(|recipient| {
+            Ok(FeeRecipient {
+                addr: deps.api.canonical_address(&recipient.addr)?,
+                weight: recipient.weight,
+            })
+        })
+        .collect()
+}
+
+/// Store new market and its paused state, returns id of the market
+/// and message to set viewing key of bet token if not set yet
+pub fn add_market<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    config: &mut Config,
+    market: MarketInfo,
+) -> StdResult<(u64, Vec<CosmosMsg>)> {
+    let market = Market {
+        bet_asset: market.bet_asset.to_raw(deps)?,
+        oracle_addr: deps.api.canonical_address(&market.oracle_addr)?,
+        oracle_code_hash: market.oracle_code_hash,
+        fee_rate: market.fee_rate,
+        fee_mode: market.fee_mode,
+        interval: market.interval,
+        grace_interval: market.grace_interval,
+        keeper_delay: market.keeper_delay,
+        keeper_bounty: market.keeper_bounty,
+        min_bet: market.min_bet,
+        max_bet_per_user: market.max_bet_per_user,
+        max_round_pool: market.max_round_pool,
+        cancel_fee_rate: market.cancel_fee_rate,
+        bucket_bounds: market.bucket_bounds,
+        draw_policy: market.draw_policy,
+        auto_payout: market.auto_payout,
+    };
+    market.validate()?;
+
+    config.market_count += 1;
+    let market_id = config.market_count;
+
+    store_market(&mut deps.storage, market_id, &market)?;
+    store_state(
+        &mut deps.storage,
+        market_id,
+        &State {
+            epoch: Uint128(0),
+            total_fee: Uint128(0),
+            paused: true,
+            open_pool: Uint128(0),
+            unclaimed_amount: Uint128(0),
+            referral_rewards: Uint128(0),
+            emergency: false,
+            emergency_time: 0,
+        },
+    )?;
+
+    let messages = set_viewing_key_msgs(deps, market.bet_asset)?;
+
+    Ok((market_id, messages))
+}
+
+/// Message to set viewing key of bet token, which is used to query balance of the contract.
+/// The key is set once for each token, so that markets betting the same token share it
+fn set_viewing_key_msgs<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    bet_asset: AssetInfoRaw,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages = vec![];
+    if let AssetInfoRaw::Token {
+        contract_addr,
+        token_code_hash,
+        viewing_key,
+    } = bet_asset
+    {
+        if read_viewing_key(&deps.storage, &contract_addr)?.is_some() {
+            return Ok(messages);
+        }
+
+        store_viewing_key(&mut deps.storage, &contract_addr, &viewing_key)?;
+        messages.push(set_viewing_key_msg(
+            viewing_key,
+            None,
+            BLOCK_SIZE,
+            token_code_hash,
+            deps.api.human_address(&contract_addr)?,
+        )?);
     }
 
-    if let Some(treasury_addr) = treasury_addr {
-        config.treasury_addr = deps.api.canonical_address(&treasury_addr)?;
+    Ok(messages)
+}
+
+pub fn update_market<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+    oracle_addr: Option<HumanAddr>,
+    oracle_code_hash: Option<String>,
+    fee_rate: Option<Decimal>,
+    fee_mode: Option<FeeMode>,
+    interval: Option<u64>,
+    grace_interval: Option<u64>,
+    keeper_delay: Option<u64>,
+    keeper_bounty: Option<Uint128>,
+    min_bet: Option<Uint128>,
+    max_bet_per_user: Option<Uint128>,
+    max_round_pool: Option<Uint128>,
+    cancel_fee_rate: Option<Decimal>,
+    draw_policy: Option<DrawPolicy>,
+    auto_payout: Option<bool>,
+) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
     }
 
+    let mut market: Market = read_market(&deps.storage, market_id)?;
+
     if let Some(oracle_addr) = oracle_addr {
-        config.oracle_addr = deps.api.canonical_address(&oracle_addr)?;
-        config.oracle_code_hash = oracle_code_hash.unwrap();
+        market.oracle_addr = deps.api.canonical_address(&oracle_addr)?;
+        market.oracle_code_hash = oracle_code_hash
+            .ok_or_else(|| StdError::generic_err("Oracle code hash is required"))?;
     }
 
     if let Some(fee_rate) = fee_rate {
-        if fee_rate > Decimal::one() {
-            return Err(StdError::generic_err("Invalid fee rate"));
-        }
-        config.fee_rate = fee_rate;
+        market.fee_rate = fee_rate;
+    }
+
+    if let Some(fee_mode) = fee_mode {
+        market.fee_mode = fee_mode;
     }
 
     if let Some(interval) = interval {
-        config.interval = interval;
+        market.interval = interval;
     }
 
     if let Some(grace_interval) = grace_interval {
-        if grace_interval > config.interval {
-            return Err(StdError::generic_err("Invalid grace interval"));
-        }
-        config.grace_interval = grace_interval;
+        market.grace_interval = grace_interval;
     }
 
-    store_config(&mut deps.storage, &config)?;
+    if let Some(keeper_delay) = keeper_delay {
+        market.keeper_delay = keeper_delay;
+    }
+
+    if let Some(keeper_bounty) = keeper_bounty {
+        market.keeper_bounty = keeper_bounty;
+    }
+
+    if let Some(min_bet) = min_bet {
+        market.min_bet = min_bet;
+    }
+
+    if let Some(max_bet_per_user) = max_bet_per_user {
+        market.max_bet_per_user = max_bet_per_user;
+    }
+
+    if let Some(max_round_pool) = max_round_pool {
+        market.max_round_pool = max_round_pool;
+    }
+
+    if let Some(cancel_fee_rate) = cancel_fee_rate {
+        market.cancel_fee_rate = cancel_fee_rate;
+    }
+
+    if let Some(draw_policy) = draw_policy {
+        market.draw_policy = draw_policy;
+    }
+
+    if let Some(auto_payout) = auto_payout {
+        market.auto_payout = auto_payout;
+    }
+
+    market.validate()?;
+
+    store_market(&mut deps.storage, market_id, &market)?;
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![log("action", "update_config"), log("status", "success")],
+        log: UpdateMarketEvent { market_id }.to_logs(),
         data: None,
     })
 }
@@ -76,31 +382,40 @@ pub fn update_config<S: Storage, A: Api, Q: Querier>(
 pub fn execute_round<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    market_id: u64,
 ) -> HandleResult {
     let config: Config = read_config(&deps.storage)?;
+    let market: Market = read_market(&deps.storage, market_id)?;
+    let executor = deps.api.canonical_address(&env.message.sender)?;
+    let is_operator = executor == config.operator_addr;
 
     // permission check
-    if deps.api.canonical_address(&env.message.sender)? != config.operator_addr {
+    if !is_operator && !config.public_execution {
         return Err(StdError::unauthorized());
     }
 
-    let mut state: State = read_state(&deps.storage)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
     if state.paused {
         return Err(StdError::generic_err("Paused"));
     }
     let progressing_epoch = (state.epoch - Uint128(1))?;
     let betting_epoch = state.epoch;
-    let mut round: Round = read_round(&deps.storage, progressing_epoch)?;
+    let mut round: Round = read_round(&deps.storage, market_id, progressing_epoch)?;
 
-    if round.expired(env.clone(), config.grace_interval) {
+    if round.expired(env.clone(), market.grace_interval) {
         return Err(StdError::generic_err("Expired"));
     }
 
-    if !round.executable(env.clone(), config.grace_interval) {
+    if !round.executable(env.clone(), market.grace_interval) {
+        return Err(StdError::generic_err("Cannot execute"));
+    }
+
+    // keepers can execute round only when operator did not execute in time
+    if !is_operator && env.block.time < round.end_time + market.keeper_delay {
         return Err(StdError::generic_err("Cannot execute"));
     }
 
-    let price_reference_data = query_price(deps, config.clone())?;
+    let price_reference_data = query_price(deps, &market)?;
     if price_reference_data.last_updated < round.start_time {
         return Err(StdError::generic_err("Price not updated"));
     }
@@ -108,62 +423,160 @@ pub fn execute_round<S: Storage, A: Api, Q: Querier>(
 
     if let Some(open_price) = round.open_price {
         round.close_price = Some(close_price);
+        if !market.bucket_bounds.is_empty() {
+            round.winning_bucket = Some(market.bucket_of(open_price, close_price));
+        }
 
-        if close_price != open_price {
-            let mut fee = round.total_amount * config.fee_rate;
+        let mut fee = Uint128(0);
+        if round.refunded() {
+            // no fee is taken from refunded round
+        } else if round.is_draw() && round.draw_policy == DrawPolicy::HouseWins {
+            round.reward_amount = Uint128(0);
+            fee = round.total_amount;
+        } else {
+            let fee_base = match market.fee_mode {
+                FeeMode::OnPool => round.total_amount,
+                FeeMode::OnProfit => (round.total_amount - round.win_bet_amount())?,
+            };
+            fee = decimal_mul(fee_base, market.fee_rate);
             round.reward_amount = (round.total_amount - fee)?;
 
-            if close_price > open_price {
-                if round.reward_amount < round.up_amount {
-                    round.reward_amount = round.total_amount;
-                    fee = Uint128(0);
-                }
-            } else {
-                if round.reward_amount < round.down_amount {
-                    round.reward_amount = round.total_amount;
-                    fee = Uint128(0);
-                }
+            // winners get back at least their bets
+            if round.reward_amount < round.win_bet_amount() {
+                round.reward_amount = round.total_amount;
+                fee = Uint128(0);
             }
+        }
 
-            state.total_fee = state.total_fee + fee;
+        // share of fee from referred bets is reserved for referrers
+        if !round.referred_amount.is_zero() {
+            round.referral_fee = decimal_mul(
+                multiply_ratio(fee, round.referred_amount, round.total_amount),
+                config.referral_fee_rate,
+            );
+        }
+        state.total_fee = state.total_fee + (fee - round.referral_fee)?;
+        let mut fee_logs = FeeAccrualEvent {
+            market_id,
+            epoch: progressing_epoch,
+            source: FeeSource::Round,
+            fee,
+            referral_fee: round.referral_fee,
+            total_fee: state.total_fee,
+        }
+        .to_logs();
+
+        // bets of finished round are moved to rewards, refunds and fee
+        state.open_pool = (state.open_pool - round.total_amount)?;
+        state.unclaimed_amount = state.unclaimed_amount
+            + if round.refunded() {
+                round.total_amount
+            } else {
+                round.reward_amount
+            };
+        state.referral_rewards = state.referral_rewards + round.referral_fee;
+
+        let mut messages = vec![];
+        let mut bounty = Uint128(0);
+        if !is_operator {
+            bounty = market.keeper_bounty.min(state.total_fee);
+            state.total_fee = (state.total_fee - bounty)?;
+            fee_logs.extend(fee_accrual_logs(
+                market_id,
+                progressing_epoch,
+                FeeSource::KeeperBounty,
+                bounty,
+                state.total_fee,
+            ));
+        }
+        if !bounty.is_zero() {
+            let bounty_asset = Asset {
+                amount: bounty,
+                info: market.bet_asset.to_normal(deps)?,
+            };
+            messages.push(
+                bounty_asset.into_msg(env.contract.address.clone(), env.message.sender.clone())?,
+            );
         }
 
+        round.executor = Some(executor);
+        round.bounty = bounty;
+
         // Store result of round
-        store_round(&mut deps.storage, progressing_epoch, &round)?;
+        store_round(&mut deps.storage, market_id, progressing_epoch, &round)?;
 
-        let mut betting_round: Round = read_round(&deps.storage, betting_epoch)?;
+        let mut betting_round: Round = read_round(&deps.storage, market_id, betting_epoch)?;
         betting_round.open_price = Some(close_price);
 
         // Lock betting round
-        store_round(&mut deps.storage, betting_epoch, &betting_round)?;
+        store_round(&mut deps.storage, market_id, betting_epoch, &betting_round)?;
 
         // Increase epoch
         state.epoch = state.epoch + Uint128(1);
-        store_state(&mut deps.storage, &state)?;
+        store_state(&mut deps.storage, market_id, &state)?;
 
         let new_round = Round {
             start_time: env.block.time,
-            lock_time: env.block.time + config.interval,
-            end_time: env.block.time + config.interval * 2,
+            lock_time: env.block.time + market.interval,
+            end_time: env.block.time + market.interval * 2,
             open_price: None,
             close_price: None,
             total_amount: Uint128(0),
             reward_amount: Uint128(0),
             up_amount: Uint128(0),
             down_amount: Uint128(0),
+            draw_amount: Uint128(0),
+            bucket_amounts: vec![],
+            winning_bucket: None,
+            referred_amount: Uint128(0),
+            referral_fee: Uint128(0),
+            referral_paid: Uint128(0),
+            referral_paid_amount: Uint128(0),
+            draw_policy: market.draw_policy.clone(),
             is_genesis: false,
+            cancelled: false,
+            executor: None,
+            bounty: Uint128(0),
+            paid_out: Uint128(0),
+            paid_bet_amount: Uint128(0),
         };
 
         // Start new round
-        store_round(&mut deps.storage, state.epoch, &new_round)?;
+        store_round(&mut deps.storage, market_id, state.epoch, &new_round)?;
+
+        let mut logs = RoundCloseEvent {
+            market_id,
+            epoch: progressing_epoch,
+            close_price,
+            total_amount: round.total_amount,
+            reward_amount: round.reward_amount,
+            executor: env.message.sender.clone(),
+            bounty,
+        }
+        .to_logs();
+        logs.extend(fee_logs);
+        logs.extend(
+            RoundLockEvent {
+                market_id,
+                epoch: betting_epoch,
+                open_price: close_price,
+                total_amount: betting_round.total_amount,
+            }
+            .to_logs(),
+        );
+        logs.extend(round_start_event(market_id, state.epoch, &new_round).to_logs());
+
+        // Pay first bettors of finished round, the rest are paid by distribute round
+        if market.auto_payout {
+            let (payout_messages, payout_logs) =
+                distribute_bets(deps, env, market_id, &market, progressing_epoch, None)?;
+            messages.extend(payout_messages);
+            logs.extend(payout_logs);
+        }
 
         Ok(HandleResponse {
-            messages: vec![],
-            log: vec![
-                log("action", "finish"),
-                log("epoch", progressing_epoch),
-                log("close_price", close_price),
-            ],
+            messages,
+            log: logs,
             data: None,
         })
     } else {
@@ -174,32 +587,48 @@ pub fn execute_round<S: Storage, A: Api, Q: Querier>(
 pub fn withdraw<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    market_id: u64,
 ) -> HandleResult {
     let config: Config = read_config(&deps.storage)?;
+    let market: Market = read_market(&deps.storage, market_id)?;
 
     // permission check
     if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
         return Err(StdError::unauthorized());
     }
 
-    let mut state: State = read_state(&deps.storage)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
 
     let total_fee = state.total_fee;
     if total_fee > Uint128(0) {
-        let return_asset = Asset {
-            amount: total_fee,
-            info: config.bet_asset.to_normal(deps)?,
-        };
+        let mut messages = vec![];
+        let mut logs = vec![];
+        for (recipient, fee) in config.split_fee(total_fee)? {
+            if fee.is_zero() {
+                continue;
+            }
+            let recipient = deps.api.human_address(&recipient)?;
+            let return_asset = Asset {
+                amount: fee,
+                info: market.bet_asset.to_normal(deps)?,
+            };
+            messages.push(return_asset.into_msg(env.contract.address.clone(), recipient.clone())?);
+            logs.extend(
+                WithdrawEvent {
+                    market_id,
+                    recipient,
+                    amount: fee,
+                }
+                .to_logs(),
+            );
+        }
         state.total_fee = Uint128(0);
 
-        store_state(&mut deps.storage, &state)?;
+        store_state(&mut deps.storage, market_id, &state)?;
 
         Ok(HandleResponse {
-            messages: vec![return_asset.into_msg(
-                env.contract.address,
-                deps.api.human_address(&config.treasury_addr)?,
-            )?],
-            log: vec![log("action", "withdraw"), log("amount", total_fee)],
+            messages,
+            log: logs,
             data: None,
         })
     } else {
@@ -207,7 +636,11 @@ pub fn withdraw<S: Storage, A: Api, Q: Querier>(
     }
 }
 
-pub fn pause<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, env: Env) -> HandleResult {
+pub fn pause<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+) -> HandleResult {
     let config: Config = read_config(&deps.storage)?;
 
     // permission check
@@ -215,18 +648,108 @@ pub fn pause<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, env: En
         return Err(StdError::unauthorized());
     }
 
-    let mut state: State = read_state(&deps.storage)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
     if state.paused {
         return Err(StdError::generic_err("Paused"));
     }
 
     state.paused = true;
 
-    store_state(&mut deps.storage, &state)?;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: PauseEvent {
+            market_id,
+            paused: true,
+        }
+        .to_logs(),
+        data: None,
+    })
+}
+
+pub fn enable_emergency<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    if state.emergency {
+        return Err(StdError::generic_err("Emergency mode"));
+    }
+
+    let mut logs = vec![];
+    for epoch in cancel_unsettled_rounds(&mut deps.storage, market_id, &mut state)? {
+        logs.extend(RoundCancelEvent { market_id, epoch }.to_logs());
+    }
+
+    state.paused = true;
+    state.emergency = true;
+    state.emergency_time = env.block.time;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    logs.extend(
+        PauseEvent {
+            market_id,
+            paused: true,
+        }
+        .to_logs(),
+    );
+    logs.extend(
+        EmergencyEvent {
+            market_id,
+            emergency: true,
+        }
+        .to_logs(),
+    );
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![log("action", "pause")],
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn disable_emergency<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
+
+    // permission check
+    if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    if !state.emergency {
+        return Err(StdError::generic_err("Not emergency mode"));
+    }
+
+    // users have enough time to notice emergency mode
+    if env.block.time < state.emergency_time + EMERGENCY_DURATION {
+        return Err(StdError::generic_err("Too early to disable emergency mode"));
+    }
+
+    // market stays paused until genesis round is started again
+    state.emergency = false;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: EmergencyEvent {
+            market_id,
+            emergency: false,
+        }
+        .to_logs(),
         data: None,
     })
 }
@@ -234,64 +757,445 @@ pub fn pause<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, env: En
 pub fn start_genesis_round<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    market_id: u64,
 ) -> HandleResult {
     let config: Config = read_config(&deps.storage)?;
+    let market: Market = read_market(&deps.storage, market_id)?;
 
     // permission check
     if deps.api.canonical_address(&env.message.sender)? != config.owner_addr {
         return Err(StdError::unauthorized());
     }
 
-    let mut state: State = read_state(&deps.storage)?;
+    let mut state: State = read_state(&deps.storage, market_id)?;
     if !state.paused {
         return Err(StdError::generic_err("Running now"));
     }
+    if state.emergency {
+        return Err(StdError::generic_err("Emergency mode"));
+    }
+
+    let mut logs = vec![];
+    for epoch in cancel_unsettled_rounds(&mut deps.storage, market_id, &mut state)? {
+        logs.extend(RoundCancelEvent { market_id, epoch }.to_logs());
+    }
+
+    logs.extend(start_rounds(
+        &mut deps.storage,
+        env,
+        market_id,
+        &market,
+        &mut state,
+    )?);
+
+    state.paused = false;
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    logs.extend(
+        PauseEvent {
+            market_id,
+            paused: false,
+        }
+        .to_logs(),
+    );
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn recover_rounds<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    market_id: u64,
+) -> HandleResult {
+    let market: Market = read_market(&deps.storage, market_id)?;
 
+    let mut state: State = read_state(&deps.storage, market_id)?;
+    if state.paused {
+        return Err(StdError::generic_err("Paused"));
+    }
+
+    let progressing_epoch = (state.epoch - Uint128(1))?;
+    let round: Round = read_round(&deps.storage, market_id, progressing_epoch)?;
+    if !round.expired(env.clone(), market.grace_interval) {
+        return Err(StdError::generic_err("Not expired"));
+    }
+
+    let mut logs = vec![];
+    for epoch in cancel_unsettled_rounds(&mut deps.storage, market_id, &mut state)? {
+        logs.extend(RoundCancelEvent { market_id, epoch }.to_logs());
+    }
+
+    logs.extend(start_rounds(
+        &mut deps.storage,
+        env,
+        market_id,
+        &market,
+        &mut state,
+    )?);
+    store_state(&mut deps.storage, market_id, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    })
+}
+
+/// Cancel locked and betting rounds which are not closed, bettors of them are refundable
+fn cancel_unsettled_rounds<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    state: &mut State,
+) -> StdResult<Vec<Uint128>> {
+    let betting_epoch = state.epoch;
+    let mut cancelled_epochs: Vec<Uint128> = vec![];
+    for epoch in (betting_epoch.u128().saturating_sub(1)..=betting_epoch.u128()).map(Uint128) {
+        if let Some(mut round) = may_read_round(storage, market_id, epoch)? {
+            if !round.settled() {
+                // bets which are not refunded yet are moved to refunds
+                let refund_amount = (round.total_amount - round.paid_out)?;
+                state.open_pool = (state.open_pool - refund_amount)?;
+                state.unclaimed_amount = state.unclaimed_amount + refund_amount;
+
+                round.cancelled = true;
+                store_round(storage, market_id, epoch, &round)?;
+                cancelled_epochs.push(epoch);
+            }
+        }
+    }
+
+    Ok(cancelled_epochs)
+}
+
+fn round_start_event(market_id: u64, epoch: Uint128, round: &Round) -> RoundStartEvent {
+    RoundStartEvent {
+        market_id,
+        epoch,
+        start_time: round.start_time,
+        lock_time: round.lock_time,
+        end_time: round.end_time,
+    }
+}
+
+/// Start genesis round which is not bettable and next betting round, returns logs of them
+fn start_rounds<S: Storage>(
+    storage: &mut S,
+    env: Env,
+    market_id: u64,
+    market: &Market,
+    state: &mut State,
+) -> StdResult<Vec<LogAttribute>> {
     let epoch = state.epoch + Uint128(1);
 
     let open_price = Uint128(0);
+    let genesis_round = Round {
+        start_time: env.block.time - market.interval,
+        lock_time: env.block.time,
+        end_time: env.block.time + market.interval,
+        open_price: Some(open_price),
+        close_price: None,
+        total_amount: Uint128(0),
+        reward_amount: Uint128(0),
+        up_amount: Uint128(0),
+        down_amount: Uint128(0),
+        draw_amount: Uint128(0),
+        bucket_amounts: vec![],
+        winning_bucket: None,
+        referred_amount: Uint128(0),
+        referral_fee: Uint128(0),
+        referral_paid: Uint128(0),
+        referral_paid_amount: Uint128(0),
+        draw_policy: market.draw_policy.clone(),
+        is_genesis: true,
+        cancelled: false,
+        executor: None,
+        bounty: Uint128(0),
+        paid_out: Uint128(0),
+        paid_bet_amount: Uint128(0),
+    };
+    let betting_round = Round {
+        start_time: env.block.time,
+        lock_time: env.block.time + market.interval,
+        end_time: env.block.time + market.interval * 2,
+        open_price: None,
+        close_price: None,
+        total_amount: Uint128(0),
+        reward_amount: Uint128(0),
+        up_amount: Uint128(0),
+        down_amount: Uint128(0),
+        draw_amount: Uint128(0),
+        bucket_amounts: vec![],
+        winning_bucket: None,
+        referred_amount: Uint128(0),
+        referral_fee: Uint128(0),
+        referral_paid: Uint128(0),
+        referral_paid_amount: Uint128(0),
+        draw_policy: market.draw_policy.clone(),
+        is_genesis: false,
+        cancelled: false,
+        executor: None,
+        bounty: Uint128(0),
+        paid_out: Uint128(0),
+        paid_bet_amount: Uint128(0),
+    };
+
     // Start genesis round
-    store_round(
-        &mut deps.storage,
-        epoch,
-        &Round {
-            start_time: env.block.time - config.interval,
-            lock_time: env.block.time,
-            end_time: env.block.time + config.interval,
-            open_price: Some(open_price),
-            close_price: None,
-            total_amount: Uint128(0),
-            reward_amount: Uint128(0),
-            up_amount: Uint128(0),
-            down_amount: Uint128(0),
-            is_genesis: true,
-        },
-    )?;
+    store_round(storage, market_id, epoch, &genesis_round)?;
+    store_round(storage, market_id, epoch + Uint128(1), &betting_round)?;
 
-    store_round(
-        &mut deps.storage,
-        epoch + Uint128(1),
-        &Round {
-            start_time: env.block.time,
-            lock_time: env.block.time + config.interval,
-            end_time: env.block.time + config.interval * 2,
-            open_price: None,
-            close_price: None,
+    state.epoch = state.epoch + Uint128(2);
+
+    let mut logs = round_start_event(market_id, epoch, &genesis_round).to_logs();
+    logs.extend(
+        RoundLockEvent {
+            market_id,
+            epoch,
+            open_price,
             total_amount: Uint128(0),
-            reward_amount: Uint128(0),
-            up_amount: Uint128(0),
-            down_amount: Uint128(0),
-            is_genesis: false,
+        }
+        .to_logs(),
+    );
+    logs.extend(round_start_event(market_id, epoch + Uint128(1), &betting_round).to_logs());
+
+    Ok(logs)
+}
+
+/// Move config, state and rounds of the single market contract into the legacy market,
+/// up to limit rounds. The market stays paused until the owner starts genesis round
+pub fn migrate_legacy<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limit: Option<u32>,
+) -> HandleResult {
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut messages = vec![];
+    let mut migration = match read_legacy_migration(&deps.storage)? {
+        Some(migration) => {
+            // permission check
+            if sender_raw != read_config(&deps.storage)?.owner_addr {
+                return Err(StdError::unauthorized());
+            }
+            migration
+        }
+        None => {
+            let legacy_config = read_legacy_config(&deps.storage)
+                .map_err(|_| StdError::generic_err("Nothing to migrate"))?;
+
+            // permission check
+            if sender_raw != legacy_config.owner_addr {
+                return Err(StdError::unauthorized());
+            }
+
+            let (migration, market_messages) = start_legacy_migration(deps, &env, legacy_config)?;
+            messages.extend(market_messages);
+            migration
+        }
+    };
+
+    let limit = limit
+        .unwrap_or(DEFAULT_MIGRATE_LIMIT)
+        .min(MAX_MIGRATE_LIMIT) as u128;
+    let last_epoch = migration.state.epoch;
+    let start_epoch = migration.migrated_epoch.u128() + 1;
+    let end_epoch = (migration.migrated_epoch.u128() + limit).min(last_epoch.u128());
+    let mut fee_logs = vec![];
+    for epoch in (start_epoch..=end_epoch).map(Uint128) {
+        if let Some(legacy_round) = read_legacy_round(&deps.storage, epoch)? {
+            let total_fee = migration.state.total_fee;
+            let round = migration.add_round(legacy_round)?;
+            fee_logs.extend(fee_accrual_logs(
+                LEGACY_MARKET_ID,
+                epoch,
+                FeeSource::LegacyRefund,
+                (total_fee - migration.state.total_fee)?,
+                migration.state.total_fee,
+            ));
+            store_round(&mut deps.storage, LEGACY_MARKET_ID, epoch, &round)?;
+            remove_legacy_round(&mut deps.storage, epoch);
+        }
+    }
+    migration.migrated_epoch = Uint128(end_epoch);
+
+    // liabilities are complete once all rounds are moved
+    if migration.migrated_epoch == last_epoch {
+        store_state(&mut deps.storage, LEGACY_MARKET_ID, &migration.state)?;
+        remove_legacy_migration(&mut deps.storage);
+    } else {
+        store_legacy_migration(&mut deps.storage, &migration)?;
+    }
+
+    let mut logs = MigrateLegacyEvent {
+        market_id: LEGACY_MARKET_ID,
+        migrated_epoch: migration.migrated_epoch,
+        last_epoch,
+        total_fee: migration.state.total_fee,
+    }
+    .to_logs();
+    logs.extend(fee_logs);
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: None,
+    })
+}
+
+/// Store config and the legacy market from config of the single market contract,
+/// returns migration with paused state of the market and message to set viewing key
+fn start_legacy_migration<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    legacy_config: LegacyConfig,
+) -> StdResult<(LegacyMigration, Vec<CosmosMsg>)> {
+    let legacy_state = read_legacy_state(&deps.storage)?;
+    remove_legacy_state(&mut deps.storage);
+
+    let config = Config {
+        contract_addr: deps.api.canonical_address(&env.contract.address)?,
+        owner_addr: legacy_config.owner_addr,
+        operator_addr: legacy_config.operator_addr,
+        fee_recipients: vec![FeeRecipient {
+            addr: legacy_config.treasury_addr,
+            weight: Decimal::one(),
+        }],
+        public_execution: false,
+        referral_fee_rate: Decimal::zero(),
+        market_count: LEGACY_MARKET_ID,
+        pending_owner: None,
+        pending_operator: None,
+    };
+    config.validate()?;
+    store_config(&mut deps.storage, &config)?;
+
+    // limits, penalties and keeper bounty were not supported
+    let market = Market {
+        bet_asset: legacy_config.bet_asset,
+        oracle_addr: legacy_config.oracle_addr,
+        oracle_code_hash: legacy_config.oracle_code_hash,
+        fee_rate: legacy_config.fee_rate,
+        fee_mode: FeeMode::OnPool,
+        interval: legacy_config.interval,
+        grace_interval: legacy_config.grace_interval,
+        keeper_delay: legacy_config.grace_interval,
+        keeper_bounty: Uint128(0),
+        min_bet: Uint128(0),
+        max_bet_per_user: Uint128(0),
+        max_round_pool: Uint128(0),
+        cancel_fee_rate: Decimal::zero(),
+        bucket_bounds: vec![],
+        draw_policy: DrawPolicy::Refund,
+        auto_payout: false,
+    };
+    market.validate()?;
+    store_market(&mut deps.storage, LEGACY_MARKET_ID, &market)?;
+
+    let messages = set_viewing_key_msgs(deps, market.bet_asset)?;
+
+    let migration = LegacyMigration {
+        state: State {
+            epoch: legacy_state.epoch,
+            total_fee: legacy_state.total_fee,
+            paused: true,
+            open_pool: Uint128(0),
+            unclaimed_amount: Uint128(0),
+            referral_rewards: Uint128(0),
+            emergency: false,
+            emergency_time: 0,
         },
-    )?;
+        migrated_epoch: Uint128(0),
+    };
 
-    state.paused = false;
-    state.epoch = state.epoch + Uint128(2);
-    store_state(&mut deps.storage, &state)?;
+    Ok((migration, messages))
+}
+
+/// Move bets of user stored by the single market contract into the legacy market
+pub fn migrate_bets<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    user: HumanAddr,
+    epochs: Vec<Uint128>,
+) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
+    let market: Market = read_market(&deps.storage, LEGACY_MARKET_ID)?;
+    let user_raw = deps.api.canonical_address(&user)?;
+
+    // permission check
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    if sender_raw != config.owner_addr && sender_raw != user_raw {
+        return Err(StdError::unauthorized());
+    }
+
+    if epochs.len() > MAX_MIGRATE_BETS {
+        return Err(StdError::generic_err("Too many epochs"));
+    }
+
+    if read_legacy_migration(&deps.storage)?.is_some() {
+        return Err(StdError::generic_err("Rounds are not migrated"));
+    }
+
+    let mut fee_logs = vec![];
+    let mut migrated: u64 = 0;
+    for epoch in epochs {
+        let bet = match read_legacy_bet(&deps.storage, epoch, &user_raw)? {
+            Some(bet) => bet,
+            None => continue,
+        };
+        store_bet(
+            &mut deps.storage,
+            LEGACY_MARKET_ID,
+            epoch,
+            user_raw.clone(),
+            &bet,
+        )?;
+        remove_legacy_bet(&mut deps.storage, epoch, &user_raw);
+
+        // claimed bets were paid by the previous version
+        if bet.claimed {
+            let mut round: Round = read_round(&deps.storage, LEGACY_MARKET_ID, epoch)?;
+            let amount = round.claimable_amount(env.clone(), bet.clone(), market.grace_interval);
+            let dust = round.record_payout(&bet, amount)?;
+            store_round(&mut deps.storage, LEGACY_MARKET_ID, epoch, &round)?;
+            fee_logs.extend(record_paid(
+                &mut deps.storage,
+                LEGACY_MARKET_ID,
+                epoch,
+                round.settled(),
+                amount,
+                dust,
+            )?);
+        }
+
+        // cursors which already passed the epoch are moved back to the bet
+        let index = count_user_bets_before(&deps.storage, LEGACY_MARKET_ID, &user_raw, epoch)?;
+        if index < read_claim_index(&deps.storage, LEGACY_MARKET_ID, &user_raw)? {
+            store_claim_index(&mut deps.storage, LEGACY_MARKET_ID, &user_raw, index)?;
+        }
+        let mut stats = read_user_stats(&deps.storage, LEGACY_MARKET_ID, &user_raw)?;
+        if index < stats.settled_bet_count {
+            // stats are not recounted, the bet is only skipped
+            stats.settled_bet_count += 1;
+            store_user_stats(&mut deps.storage, LEGACY_MARKET_ID, &user_raw, &stats)?;
+        }
+
+        migrated += 1;
+    }
+
+    let mut logs = MigrateBetsEvent {
+        market_id: LEGACY_MARKET_ID,
+        user,
+        migrated,
+    }
+    .to_logs();
+    logs.extend(fee_logs);
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![log("action", "pause")],
+        log: logs,
         data: None,
     })
 }
@@ -0,0 +1,58 @@
+use cosmwasm_std::{Decimal, Uint128};
+
+const DECIMAL_FRACTIONAL: u128 = 1_000_000_000_000_000_000;
+
+/// `amount * nom / denom` rounded down. The product is kept in 256 bits, since amounts of
+/// 18 decimal tokens overflow `Uint128::multiply_ratio`. Result must fit in 128 bits,
+/// which holds whenever `nom` does not exceed `denom`
+pub fn multiply_ratio(amount: Uint128, nom: Uint128, denom: Uint128) -> Uint128 {
+    let (hi, lo) = full_mul(amount.u128(), nom.u128());
+    Uint128(div_wide(hi, lo, denom.u128()))
+}
+
+/// `amount * rate` rounded down, for rates up to 1
+pub fn decimal_mul(amount: Uint128, rate: Decimal) -> Uint128 {
+    // atomics of rate are exact as long as rate does not exceed 340
+    let rate_atomics = Uint128(DECIMAL_FRACTIONAL) * rate;
+    multiply_ratio(amount, rate_atomics, Uint128(DECIMAL_FRACTIONAL))
+}
+
+/// 256 bit product of two numbers, as high and low 128 bits
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+
+    let mid = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (lo_lo & MASK) | (mid << 64);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// Long division of 256 bit number by 128 bit divisor
+fn div_wide(hi: u128, lo: u128, divisor: u128) -> u128 {
+    if divisor == 0 {
+        panic!("Division by zero");
+    }
+    if hi >= divisor {
+        panic!("Multiplication overflow");
+    }
+
+    let mut rem = hi;
+    let mut quot: u128 = 0;
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quot <<= 1;
+        if carry == 1 || rem >= divisor {
+            rem = rem.wrapping_sub(divisor);
+            quot |= 1;
+        }
+    }
+    quot
+}
@@ -3,9 +3,27 @@ use cosmwasm_std::{
     WasmQuery,
 };
 
-use crate::state::{read_bet, read_config, read_round, read_state, Bet, Config, Round, State};
+use secret_toolkit::snip20::balance_query;
+
+use crate::manage::BLOCK_SIZE;
+use crate::state::{
+    count_user_bets_before, may_read_round, read_bet, read_claimers, read_config, read_leaderboard,
+    read_market, read_referral, read_referral_rounds, read_referred_users, read_round, read_state,
+    read_user_bet_count, read_user_bet_epoch, read_user_stats, read_viewing_key, Bet, Config,
+    Market, Referral, Round, State, UserStats, LEADERBOARD_SIZE,
+};
+use scrt_prediction::asset::AssetInfoRaw;
 use scrt_prediction::oracle::{PriceData, QueryMsg as OracleQueryMsg};
-use scrt_prediction::prediction::ConfigResponse;
+use scrt_prediction::prediction::{
+    AssetSolvencyResponse, BetResult, ClaimersResponse, ConfigResponse, FeeRecipientInfo,
+    FeeRecipientResponse, FeeRecipientsResponse, LeaderboardEntry, LeaderboardMetric,
+    LeaderboardResponse, MarketResponse, MarketsResponse, OrderBy, ReferralStatsResponse,
+    RoundResponse, RoundsResponse, SolvencyResponse, UserBetResponse, UserBetsResponse,
+    UserStatsResponse,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
 
 pub fn query_config<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
@@ -14,47 +32,455 @@ pub fn query_config<S: Storage, A: Api, Q: Querier>(
     let resp = ConfigResponse {
         owner_addr: deps.api.human_address(&config.owner_addr)?,
         operator_addr: deps.api.human_address(&config.operator_addr)?,
-        treasury_addr: deps.api.human_address(&config.treasury_addr)?,
-        bet_asset: config.bet_asset.to_normal(deps)?,
-        oracle_addr: deps.api.human_address(&config.oracle_addr)?,
-        oracle_code_hash: config.oracle_code_hash,
-        fee_rate: config.fee_rate,
-        interval: config.interval,
-        grace_interval: config.grace_interval,
+        fee_recipients: config
+            .fee_recipients
+            .iter()
+            .map(|recipient| {
+                Ok(FeeRecipientInfo {
+                    addr: deps.api.human_address(&recipient.addr)?,
+                    weight: recipient.weight,
+                })
+            })
+            .collect::<StdResult<Vec<FeeRecipientInfo>>>()?,
+        public_execution: config.public_execution,
+        referral_fee_rate: config.referral_fee_rate,
+        market_count: config.market_count,
+        pending_owner: config
+            .pending_owner
+            .map(|pending| pending.to_normal(deps))
+            .transpose()?,
+        pending_operator: config
+            .pending_operator
+            .map(|pending| pending.to_normal(deps))
+            .transpose()?,
     };
 
     Ok(resp)
 }
 
-pub fn query_state<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<State> {
-    let state: State = read_state(&deps.storage)?;
+pub fn query_market<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+) -> StdResult<MarketResponse> {
+    let market: Market = read_market(&deps.storage, market_id)?;
+    market_response(deps, market_id, market)
+}
+
+pub fn query_markets<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<MarketsResponse> {
+    let config: Config = read_config(&deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as u64;
+
+    // markets are stored in sequential ids starting from 1
+    let start = start_after
+        .map(|market_id| market_id + 1)
+        .unwrap_or(1)
+        .max(1);
+    let end = (start + limit).min(config.market_count + 1);
+
+    let mut markets: Vec<MarketResponse> = vec![];
+    for market_id in start..end {
+        let market: Market = read_market(&deps.storage, market_id)?;
+        markets.push(market_response(deps, market_id, market)?);
+    }
+
+    Ok(MarketsResponse { markets })
+}
+
+fn market_response<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    market: Market,
+) -> StdResult<MarketResponse> {
+    Ok(MarketResponse {
+        market_id,
+        bet_asset: market.bet_asset.to_normal(deps)?,
+        oracle_addr: deps.api.human_address(&market.oracle_addr)?,
+        oracle_code_hash: market.oracle_code_hash,
+        fee_rate: market.fee_rate,
+        fee_mode: market.fee_mode,
+        interval: market.interval,
+        grace_interval: market.grace_interval,
+        keeper_delay: market.keeper_delay,
+        keeper_bounty: market.keeper_bounty,
+        min_bet: market.min_bet,
+        max_bet_per_user: market.max_bet_per_user,
+        max_round_pool: market.max_round_pool,
+        cancel_fee_rate: market.cancel_fee_rate,
+        bucket_bounds: market.bucket_bounds,
+        draw_policy: market.draw_policy,
+        auto_payout: market.auto_payout,
+    })
+}
+
+pub fn query_state<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+) -> StdResult<State> {
+    let state: State = read_state(&deps.storage, market_id)?;
     Ok(state)
 }
 
 pub fn query_round<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
+    market_id: u64,
     epoch: Uint128,
 ) -> StdResult<Round> {
-    let round: Round = read_round(&deps.storage, epoch)?;
+    let round: Round = read_round(&deps.storage, market_id, epoch)?;
     Ok(round)
 }
 
+pub fn query_rounds<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    start_after: Option<Uint128>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<RoundsResponse> {
+    let state: State = read_state(&deps.storage, market_id)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as u128;
+    let last_epoch = state.epoch.u128();
+
+    // rounds are stored in sequential epochs starting from 1
+    let epochs: Vec<u128> = match order.unwrap_or(OrderBy::Asc) {
+        OrderBy::Asc => {
+            let start = start_after
+                .map(|epoch| epoch.u128() + 1)
+                .unwrap_or(1)
+                .max(1);
+            let end = (start + limit).min(last_epoch + 1);
+            (start..end).collect()
+        }
+        OrderBy::Desc => {
+            let end = start_after
+                .map(|epoch| epoch.u128())
+                .unwrap_or(last_epoch + 1)
+                .min(last_epoch + 1);
+            let start = end.saturating_sub(limit).max(1);
+            (start..end).rev().collect()
+        }
+    };
+
+    let mut rounds: Vec<RoundResponse> = vec![];
+    for epoch in epochs {
+        if let Some(round) = may_read_round(&deps.storage, market_id, Uint128(epoch))? {
+            rounds.push(RoundResponse {
+                epoch: Uint128(epoch),
+                start_time: round.start_time,
+                lock_time: round.lock_time,
+                end_time: round.end_time,
+                open_price: round.open_price,
+                close_price: round.close_price,
+                total_amount: round.total_amount,
+                reward_amount: round.reward_amount,
+                up_amount: round.up_amount,
+                down_amount: round.down_amount,
+                draw_amount: round.draw_amount,
+                bucket_amounts: round.bucket_amounts.clone(),
+                winning_bucket: round.winning_bucket,
+                draw_policy: round.draw_policy.clone(),
+                status: round.status(),
+                executor: match round.executor {
+                    Some(executor) => Some(deps.api.human_address(&executor)?),
+                    None => None,
+                },
+                bounty: round.bounty,
+                paid_out: round.paid_out,
+            });
+        }
+    }
+
+    Ok(RoundsResponse { rounds })
+}
+
 pub fn query_bet<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
+    market_id: u64,
     epoch: Uint128,
     user: HumanAddr,
 ) -> StdResult<Bet> {
-    let bet: Bet = read_bet(&deps.storage, epoch, deps.api.canonical_address(&user)?)?;
+    let bet: Bet = read_bet(
+        &deps.storage,
+        market_id,
+        epoch,
+        deps.api.canonical_address(&user)?,
+    )?;
     Ok(bet)
 }
 
+pub fn query_user_bets<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    user: HumanAddr,
+    start_after: Option<Uint128>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<UserBetsResponse> {
+    let user = deps.api.canonical_address(&user)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as u64;
+
+    // user bet index is sorted by epoch
+    let indexes: Vec<u64> = match order.unwrap_or(OrderBy::Asc) {
+        OrderBy::Asc => {
+            let start = match start_after {
+                Some(epoch) => {
+                    count_user_bets_before(&deps.storage, market_id, &user, epoch + Uint128(1))?
+                }
+                None => 0,
+            };
+            let end = (start + limit).min(read_user_bet_count(&deps.storage, market_id, &user)?);
+            (start..end).collect()
+        }
+        OrderBy::Desc => {
+            let end = match start_after {
+                Some(epoch) => count_user_bets_before(&deps.storage, market_id, &user, epoch)?,
+                None => read_user_bet_count(&deps.storage, market_id, &user)?,
+            };
+            let start = end.saturating_sub(limit);
+            (start..end).rev().collect()
+        }
+    };
+
+    let mut bets: Vec<UserBetResponse> = vec![];
+    for index in indexes {
+        let epoch = read_user_bet_epoch(&deps.storage, market_id, &user, index)?;
+        let bet: Bet = read_bet(&deps.storage, market_id, epoch, user.clone())?;
+        let round: Round = read_round(&deps.storage, market_id, epoch)?;
+
+        let result = round.bet_result(&bet);
+        let claimable_amount = if bet.claimed {
+            Uint128(0)
+        } else {
+            match result {
+                BetResult::Won => round.reward_of(&bet),
+                BetResult::Refund => bet.amount,
+                _ => Uint128(0),
+            }
+        };
+
+        bets.push(UserBetResponse {
+            epoch,
+            amount: bet.amount,
+            position: bet.position,
+            claimed: bet.claimed,
+            result,
+            claimable_amount,
+        });
+    }
+
+    Ok(UserBetsResponse { bets })
+}
+
+pub fn query_fee_recipients<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+) -> StdResult<FeeRecipientsResponse> {
+    let config: Config = read_config(&deps.storage)?;
+    let state: State = read_state(&deps.storage, market_id)?;
+
+    let mut recipients: Vec<FeeRecipientResponse> = vec![];
+    for (recipient, (_, amount)) in config
+        .fee_recipients
+        .iter()
+        .zip(config.split_fee(state.total_fee)?)
+    {
+        recipients.push(FeeRecipientResponse {
+            addr: deps.api.human_address(&recipient.addr)?,
+            weight: recipient.weight,
+            amount,
+        });
+    }
+
+    Ok(FeeRecipientsResponse { recipients })
+}
+
+pub fn query_referral_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    referrer: HumanAddr,
+) -> StdResult<ReferralStatsResponse> {
+    let referrer_raw = deps.api.canonical_address(&referrer)?;
+    let referral: Referral = read_referral(&deps.storage, market_id, &referrer_raw)?;
+    let claimable_amount = read_referral_rounds(
+        &deps.storage,
+        market_id,
+        &referrer_raw,
+        &referral,
+        MAX_LIMIT as u64,
+    )?
+    .iter()
+    .fold(Uint128(0), |total, (_, round, referred_amount)| {
+        total + round.referral_reward_of(*referred_amount)
+    });
+
+    Ok(ReferralStatsResponse {
+        referrer,
+        referred_users: read_referred_users(&deps.storage, &referrer_raw)?,
+        referred_amount: referral.referred_amount,
+        claimed_amount: referral.claimed_amount,
+        claimable_amount,
+    })
+}
+
+pub fn query_user_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    user: HumanAddr,
+) -> StdResult<UserStatsResponse> {
+    let user_raw = deps.api.canonical_address(&user)?;
+    let stats: UserStats = read_user_stats(&deps.storage, market_id, &user_raw)?;
+    let net_pnl = if stats.total_won >= stats.total_wagered {
+        (stats.total_won - stats.total_wagered)?.to_string()
+    } else {
+        format!("-{}", (stats.total_wagered - stats.total_won)?)
+    };
+
+    Ok(UserStatsResponse {
+        user,
+        rounds_played: stats.rounds_played,
+        total_wagered: stats.total_wagered,
+        total_won: stats.total_won,
+        net_pnl,
+        current_streak: stats.current_streak,
+        best_streak: stats.best_streak,
+    })
+}
+
+pub fn query_leaderboard<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    market_id: u64,
+    metric: LeaderboardMetric,
+    limit: Option<u32>,
+) -> StdResult<LeaderboardResponse> {
+    let limit = (limit.unwrap_or(DEFAULT_LIMIT) as usize).min(LEADERBOARD_SIZE);
+    let entries = read_leaderboard(&deps.storage, market_id, &metric)?
+        .iter()
+        .take(limit)
+        .map(|entry| {
+            Ok(LeaderboardEntry {
+                user: deps.api.human_address(&entry.user)?,
+                value: entry.value,
+            })
+        })
+        .collect::<StdResult<Vec<LeaderboardEntry>>>()?;
+
+    Ok(LeaderboardResponse { entries })
+}
+
+pub fn query_claimers<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    user: HumanAddr,
+) -> StdResult<ClaimersResponse> {
+    let user_raw = deps.api.canonical_address(&user)?;
+    let claimers = read_claimers(&deps.storage, &user_raw)?
+        .iter()
+        .map(|claimer| deps.api.human_address(claimer))
+        .collect::<StdResult<Vec<HumanAddr>>>()?;
+
+    Ok(ClaimersResponse { user, claimers })
+}
+
+pub fn query_solvency<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SolvencyResponse> {
+    let config: Config = read_config(&deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as u64;
+    let start = start_after
+        .map(|market_id| market_id + 1)
+        .unwrap_or(1)
+        .max(1);
+    let end = (start + limit).min(config.market_count + 1);
+
+    // sum up liabilities of markets by bet asset, markets betting the same token
+    // are grouped by token address regardless of viewing key of the market
+    let mut assets: Vec<(AssetInfoRaw, State)> = vec![];
+    for market_id in start..end {
+        let market: Market = read_market(&deps.storage, market_id)?;
+        let state: State = read_state(&deps.storage, market_id)?;
+        match assets
+            .iter_mut()
+            .find(|(asset, _)| asset.as_bytes() == market.bet_asset.as_bytes())
+        {
+            Some((_, total)) => {
+                total.open_pool = total.open_pool + state.open_pool;
+                total.unclaimed_amount = total.unclaimed_amount + state.unclaimed_amount;
+                total.referral_rewards = total.referral_rewards + state.referral_rewards;
+                total.total_fee = total.total_fee + state.total_fee;
+            }
+            None => assets.push((market.bet_asset, state)),
+        }
+    }
+
+    // balance is shared by all markets betting the asset
+    let all_counted = start == 1 && end == config.market_count + 1;
+    let contract_addr = deps.api.human_address(&config.contract_addr)?;
+    let assets = assets
+        .into_iter()
+        .map(|(asset, total)| {
+            let balance = match &asset {
+                AssetInfoRaw::NativeToken { denom } => {
+                    deps.querier
+                        .query_balance(contract_addr.clone(), denom)?
+                        .amount
+                }
+                AssetInfoRaw::Token {
+                    contract_addr: token_addr,
+                    token_code_hash,
+                    viewing_key,
+                } => {
+                    // key set on the token for the first market betting it
+                    let viewing_key = read_viewing_key(&deps.storage, token_addr)?
+                        .unwrap_or_else(|| viewing_key.clone());
+                    balance_query(
+                        &deps.querier,
+                        contract_addr.clone(),
+                        viewing_key,
+                        BLOCK_SIZE,
+                        token_code_hash.clone(),
+                        deps.api.human_address(token_addr)?,
+                    )?
+                    .amount
+                }
+            };
+            let liabilities =
+                total.open_pool + total.unclaimed_amount + total.referral_rewards + total.total_fee;
+
+            Ok(AssetSolvencyResponse {
+                asset: asset.to_normal(deps)?,
+                open_pool: total.open_pool,
+                unclaimed_amount: total.unclaimed_amount,
+                referral_rewards: total.referral_rewards,
+                total_fee: total.total_fee,
+                liabilities,
+                balance,
+                solvent: if all_counted {
+                    Some(balance >= liabilities)
+                } else {
+                    None
+                },
+            })
+        })
+        .collect::<StdResult<Vec<AssetSolvencyResponse>>>()?;
+
+    Ok(SolvencyResponse {
+        assets,
+        last_market_id: end - 1,
+        market_count: config.market_count,
+    })
+}
+
 pub fn query_price<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
-    config: Config,
+    market: &Market,
 ) -> StdResult<PriceData> {
     let price_data: PriceData = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-        contract_addr: deps.api.human_address(&config.oracle_addr)?,
-        callback_code_hash: config.oracle_code_hash,
+        contract_addr: deps.api.human_address(&market.oracle_addr)?,
+        callback_code_hash: market.oracle_code_hash.clone(),
         msg: to_binary(&OracleQueryMsg::QueryLatestPrice {})?,
     }))?;
 
@@ -1,26 +1,83 @@
-use cosmwasm_std::{CanonicalAddr, Decimal, Env, StdResult, Storage, Uint128};
+use cosmwasm_std::{CanonicalAddr, Decimal, Env, StdError, StdResult, Storage, Uint128};
 use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::math::{decimal_mul, multiply_ratio};
 use scrt_prediction::asset::AssetInfoRaw;
-use scrt_prediction::prediction::Position;
+use scrt_prediction::ownership::PendingTransfer;
+use scrt_prediction::prediction::{
+    BetResult, DrawPolicy, FeeMode, LeaderboardMetric, Position, RoundStatus,
+};
 
 static KEY_CONFIG: &[u8] = b"config";
-static KEY_STATE: &[u8] = b"state";
+static PREFIX_MARKET: &[u8] = b"market";
+static PREFIX_STATE: &[u8] = b"state";
 static PREFIX_ROUND: &[u8] = b"round";
+static PREFIX_BET: &[u8] = b"bet";
+static PREFIX_USER_BET: &[u8] = b"user_bet";
+static PREFIX_USER_BET_COUNT: &[u8] = b"user_bet_count";
+static PREFIX_CLAIM_INDEX: &[u8] = b"claim_index";
+static PREFIX_ROUND_BET: &[u8] = b"round_bet";
+static PREFIX_ROUND_BET_COUNT: &[u8] = b"round_bet_count";
+static PREFIX_REFERRER: &[u8] = b"referrer";
+static PREFIX_CLAIMERS: &[u8] = b"claimers";
+static PREFIX_REFERRED_USERS: &[u8] = b"referred_users";
+static PREFIX_REFERRAL: &[u8] = b"referral";
+static PREFIX_REFERRAL_EPOCH: &[u8] = b"referral_epoch";
+static PREFIX_REFERRAL_BET: &[u8] = b"referral_bet";
+static PREFIX_DISTRIBUTED_COUNT: &[u8] = b"distributed_count";
+static PREFIX_USER_STATS: &[u8] = b"user_stats";
+static PREFIX_LEADERBOARD: &[u8] = b"leaderboard";
+static PREFIX_VIEWING_KEY: &[u8] = b"viewing_key";
+static KEY_LEGACY_STATE: &[u8] = b"state";
+static KEY_LEGACY_MIGRATION: &[u8] = b"legacy_migration";
+
+/// Number of users kept in each leaderboard
+pub const LEADERBOARD_SIZE: usize = 50;
+/// Market which storage of the single market contract is moved into
+pub const LEGACY_MARKET_ID: u64 = 1;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
+    /// Address of this contract, to query its balance
+    pub contract_addr: CanonicalAddr,
     pub owner_addr: CanonicalAddr,
     pub operator_addr: CanonicalAddr,
-    pub treasury_addr: CanonicalAddr,
+    pub fee_recipients: Vec<FeeRecipient>,
+    pub public_execution: bool,
+    pub referral_fee_rate: Decimal,
+    pub market_count: u64,
+    /// Proposed owner which has not accepted ownership yet
+    pub pending_owner: Option<PendingTransfer>,
+    /// Proposed operator which has not accepted yet
+    pub pending_operator: Option<PendingTransfer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRecipient {
+    pub addr: CanonicalAddr,
+    pub weight: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Market {
     pub bet_asset: AssetInfoRaw,
     pub oracle_addr: CanonicalAddr,
     pub oracle_code_hash: String,
     pub fee_rate: Decimal,
+    pub fee_mode: FeeMode,
     pub interval: u64,
     pub grace_interval: u64,
+    pub keeper_delay: u64,
+    pub keeper_bounty: Uint128,
+    pub min_bet: Uint128,
+    pub max_bet_per_user: Uint128,
+    pub max_round_pool: Uint128,
+    pub cancel_fee_rate: Decimal,
+    pub bucket_bounds: Vec<i64>,
+    pub draw_policy: DrawPolicy,
+    pub auto_payout: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -28,6 +85,16 @@ pub struct State {
     pub epoch: Uint128,
     pub total_fee: Uint128,
     pub paused: bool,
+    /// Bets of rounds which are not settled
+    pub open_pool: Uint128,
+    /// Rewards and refunds of settled rounds which are not paid
+    pub unclaimed_amount: Uint128,
+    /// Referral fee reserved for referrers which is not claimed
+    pub referral_rewards: Uint128,
+    /// Market is paused with unsettled rounds cancelled, so all unsettled bets are refundable
+    pub emergency: bool,
+    /// Time when emergency mode was enabled
+    pub emergency_time: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -41,48 +108,240 @@ pub struct Round {
     pub reward_amount: Uint128,
     pub up_amount: Uint128,
     pub down_amount: Uint128,
+    pub draw_amount: Uint128,
+    pub bucket_amounts: Vec<Uint128>,
+    pub winning_bucket: Option<u8>,
+    /// Bet amount of users with referrer
+    pub referred_amount: Uint128,
+    /// Fee reserved for referrers
+    pub referral_fee: Uint128,
+    /// Referral fee paid to referrers, including dust added to fee
+    pub referral_paid: Uint128,
+    /// Referred bet amount whose referrers are paid
+    pub referral_paid_amount: Uint128,
+    /// Draw policy of market when round started
+    pub draw_policy: DrawPolicy,
     pub is_genesis: bool,
+    pub cancelled: bool,
+    pub executor: Option<CanonicalAddr>,
+    pub bounty: Uint128,
+    /// Total amount paid to bettors, including reward dust added to fee
+    pub paid_out: Uint128,
+    /// Total bet amount of winners who are paid
+    pub paid_bet_amount: Uint128,
+}
+
+impl Config {
+    pub fn validate(&self) -> StdResult<()> {
+        if self.referral_fee_rate > Decimal::one() {
+            return Err(StdError::generic_err("Invalid referral fee rate"));
+        }
+
+        let total_weight = self
+            .fee_recipients
+            .iter()
+            .fold(Decimal::zero(), |total, recipient| total + recipient.weight);
+        if total_weight != Decimal::one() {
+            return Err(StdError::generic_err("Invalid fee recipients"));
+        }
+
+        Ok(())
+    }
+
+    /// Split fee by weights of recipients, the last recipient takes the remainder
+    pub fn split_fee(&self, total_fee: Uint128) -> StdResult<Vec<(CanonicalAddr, Uint128)>> {
+        let mut remaining_fee = total_fee;
+        let mut fees = vec![];
+        for (index, recipient) in self.fee_recipients.iter().enumerate() {
+            let fee = if index + 1 == self.fee_recipients.len() {
+                remaining_fee
+            } else {
+                decimal_mul(total_fee, recipient.weight)
+            };
+            remaining_fee = (remaining_fee - fee)?;
+            fees.push((recipient.addr.clone(), fee));
+        }
+        Ok(fees)
+    }
+}
+
+impl Market {
+    pub fn validate(&self) -> StdResult<()> {
+        if self.fee_rate > Decimal::one() {
+            return Err(StdError::generic_err("Invalid fee rate"));
+        }
+
+        if self.cancel_fee_rate > Decimal::one() {
+            return Err(StdError::generic_err("Invalid cancel fee rate"));
+        }
+
+        if self.grace_interval > self.interval {
+            return Err(StdError::generic_err("Invalid grace interval"));
+        }
+
+        if self.keeper_delay > self.grace_interval {
+            return Err(StdError::generic_err("Invalid keeper delay"));
+        }
+
+        // zero max values mean no limit
+        if (!self.max_bet_per_user.is_zero() && self.max_bet_per_user < self.min_bet)
+            || (!self.max_round_pool.is_zero() && self.max_round_pool < self.max_bet_per_user)
+        {
+            return Err(StdError::generic_err("Invalid bet limits"));
+        }
+
+        if self.bucket_bounds.len() >= u8::MAX as usize
+            || self
+                .bucket_bounds
+                .windows(2)
+                .any(|bounds| bounds[0] >= bounds[1])
+        {
+            return Err(StdError::generic_err("Invalid bucket bounds"));
+        }
+
+        // range rounds always have winning bucket
+        if !self.bucket_bounds.is_empty() && self.draw_policy != DrawPolicy::Refund {
+            return Err(StdError::generic_err("Invalid draw policy"));
+        }
+
+        Ok(())
+    }
+
+    pub fn valid_position(&self, position: &Position) -> bool {
+        match position {
+            Position::UP | Position::DOWN | Position::DRAW => self.bucket_bounds.is_empty(),
+            Position::BUCKET(bucket) => {
+                !self.bucket_bounds.is_empty() && *bucket as usize <= self.bucket_bounds.len()
+            }
+        }
+    }
+
+    /// Bucket which price change falls into, lower bound is inclusive
+    pub fn bucket_of(&self, open_price: Uint128, close_price: Uint128) -> u8 {
+        let change = if open_price.is_zero() {
+            0
+        } else {
+            ((close_price.u128() as i128 - open_price.u128() as i128) * 10000)
+                .div_euclid(open_price.u128() as i128)
+        };
+
+        self.bucket_bounds
+            .iter()
+            .take_while(|bound| change >= **bound as i128)
+            .count() as u8
+    }
 }
 
 impl Round {
     pub fn bettable(&self, env: Env) -> bool {
         !self.is_genesis
+            && !self.cancelled
             && env.block.time >= self.start_time
             && env.block.time <= self.lock_time
             && self.open_price.is_none()
             && self.close_price.is_none()
     }
 
+    pub fn valid_position(&self, position: &Position) -> bool {
+        match position {
+            Position::DRAW => self.draw_policy == DrawPolicy::Draw,
+            _ => true,
+        }
+    }
+
     pub fn claimable(&self, env: Env) -> bool {
-        env.block.time >= self.end_time
+        !self.cancelled
+            && env.block.time >= self.end_time
             && self.open_price.is_some()
             && self.close_price.is_some()
-            && Some(self.open_price) != Some(self.close_price)
+            && !self.refunded()
     }
 
     pub fn refundable(&self, env: Env, grace_interval: u64) -> bool {
-        (env.block.time >= self.end_time
+        self.cancelled
+            || (env.block.time >= self.end_time && self.close_price.is_some() && self.refunded())
+            || (self.close_price.is_none() && env.block.time > self.end_time + grace_interval)
+    }
+
+    /// UP/DOWN round closed with unchanged price, range rounds always have winning bucket
+    pub fn is_draw(&self) -> bool {
+        self.winning_bucket.is_none()
             && self.open_price.is_some()
             && self.close_price.is_some()
-            && Some(self.open_price) == Some(self.close_price))
-            || (self.close_price.is_none() && env.block.time > self.end_time + grace_interval)
+            && self.open_price == self.close_price
+    }
+
+    /// Draw is refunded to all bettors by refund draw policy
+    pub fn draw_refunded(&self) -> bool {
+        self.is_draw() && self.draw_policy == DrawPolicy::Refund
+    }
+
+    /// All bets are on a single position, so there is no one to win from
+    pub fn is_one_sided(&self) -> bool {
+        let bet_positions = [self.up_amount, self.down_amount, self.draw_amount]
+            .iter()
+            .chain(self.bucket_amounts.iter())
+            .filter(|amount| !amount.is_zero())
+            .count();
+
+        !self.total_amount.is_zero() && bet_positions < 2
+    }
+
+    /// Nobody bet on the winning position, such as an empty winning bucket.
+    /// Draw which is not bettable is settled by draw policy instead
+    pub fn has_no_winner(&self) -> bool {
+        self.close_price.is_some()
+            && !self.total_amount.is_zero()
+            && !(self.is_draw() && self.draw_policy != DrawPolicy::Draw)
+            && self.win_bet_amount().is_zero()
+    }
+
+    /// Bets of closed round are refunded in full without fee
+    pub fn refunded(&self) -> bool {
+        self.is_one_sided() || self.draw_refunded() || self.has_no_winner()
+    }
+
+    pub fn is_winner(&self, position: &Position) -> bool {
+        match (self.open_price, self.close_price) {
+            (Some(open_price), Some(close_price)) => match position {
+                Position::UP => close_price > open_price,
+                Position::DOWN => close_price < open_price,
+                Position::DRAW => close_price == open_price && self.draw_policy == DrawPolicy::Draw,
+                Position::BUCKET(bucket) => self.winning_bucket == Some(*bucket),
+            },
+            _ => false,
+        }
+    }
+
+    /// Total bet amount of winning position
+    pub fn win_bet_amount(&self) -> Uint128 {
+        match (self.open_price, self.close_price, self.winning_bucket) {
+            (_, _, Some(bucket)) => self.bucket_amount(bucket),
+            (Some(open_price), Some(close_price), None) => {
+                if close_price > open_price {
+                    self.up_amount
+                } else if close_price < open_price {
+                    self.down_amount
+                } else if self.draw_policy == DrawPolicy::Draw {
+                    self.draw_amount
+                } else {
+                    Uint128(0)
+                }
+            }
+            _ => Uint128(0),
+        }
+    }
+
+    pub fn bucket_amount(&self, bucket: u8) -> Uint128 {
+        self.bucket_amounts
+            .get(bucket as usize)
+            .copied()
+            .unwrap_or(Uint128(0))
     }
 
     pub fn claimable_amount(&self, env: Env, user_bet: Bet, grace_interval: u64) -> Uint128 {
         if self.claimable(env.clone()) {
-            let win_bet_amount = if Some(self.close_price) > Some(self.open_price)
-                && user_bet.position == Position::UP
-            {
-                self.up_amount
-            } else if Some(self.close_price) < Some(self.open_price)
-                && user_bet.position == Position::DOWN
-            {
-                self.down_amount
-            } else {
-                Uint128(0)
-            };
-
-            return self.reward_amount * Decimal::from_ratio(user_bet.amount, win_bet_amount);
+            return self.reward_of(&user_bet);
         }
         if self.refundable(env, grace_interval) {
             return user_bet.amount;
@@ -90,8 +349,76 @@ impl Round {
         Uint128(0)
     }
 
+    /// Share of reward for winning bet rounded down, zero for losing bet.
+    /// Winners get back at least their bets since reward is never less than winning bets
+    pub fn reward_of(&self, user_bet: &Bet) -> Uint128 {
+        let win_bet_amount = if self.is_winner(&user_bet.position) {
+            self.win_bet_amount()
+        } else {
+            Uint128(0)
+        };
+
+        if win_bet_amount.is_zero() {
+            return Uint128(0);
+        }
+
+        multiply_ratio(self.reward_amount, user_bet.amount, win_bet_amount)
+    }
+
+    /// Record amount paid for bet, returns dust of reward left by rounding
+    /// once all winners are paid, which is counted as paid out
+    pub fn record_payout(&mut self, user_bet: &Bet, amount: Uint128) -> StdResult<Uint128> {
+        self.paid_out = self.paid_out + amount;
+
+        if self.cancelled
+            || self.refunded()
+            || user_bet.amount.is_zero()
+            || !self.is_winner(&user_bet.position)
+        {
+            return Ok(Uint128(0));
+        }
+
+        self.paid_bet_amount = self.paid_bet_amount + user_bet.amount;
+        if self.paid_bet_amount != self.win_bet_amount() {
+            return Ok(Uint128(0));
+        }
+
+        let dust = (self.reward_amount - self.paid_out)?;
+        self.paid_out = self.paid_out + dust;
+        Ok(dust)
+    }
+
+    /// Share of referral fee for referred bet amount of a referrer rounded down
+    pub fn referral_reward_of(&self, referred_amount: Uint128) -> Uint128 {
+        if self.referred_amount.is_zero() {
+            return Uint128(0);
+        }
+
+        multiply_ratio(self.referral_fee, referred_amount, self.referred_amount)
+    }
+
+    /// Record referral fee paid for referred bet amount, returns dust of referral fee
+    /// left by rounding once all referrers are paid, which is counted as paid
+    pub fn record_referral_payout(
+        &mut self,
+        referred_amount: Uint128,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        self.referral_paid = self.referral_paid + amount;
+        self.referral_paid_amount = self.referral_paid_amount + referred_amount;
+
+        if referred_amount.is_zero() || self.referral_paid_amount != self.referred_amount {
+            return Ok(Uint128(0));
+        }
+
+        let dust = (self.referral_fee - self.referral_paid)?;
+        self.referral_paid = self.referral_paid + dust;
+        Ok(dust)
+    }
+
     pub fn executable(&self, env: Env, grace_interval: u64) -> bool {
-        env.block.time >= self.end_time
+        !self.cancelled
+            && env.block.time >= self.end_time
             && env.block.time <= self.end_time + grace_interval
             && self.open_price.is_some()
             && self.close_price.is_none()
@@ -101,15 +428,81 @@ impl Round {
         env.block.time > self.end_time + grace_interval && self.close_price.is_none()
     }
 
-    // pub fn win_position(&self) -> Position {
-    //     if Some(self.open_price) > Some(self.close_price) {
-    //         Position::DOWN
-    //     } else if Some(self.open_price) > Some(self.close_price) {
-    //         Position::UP
-    //     } else {
-    //         Position::DRAW
-    //     }
-    // }
+    pub fn add_bet_amount(&mut self, position: &Position, amount: Uint128) {
+        self.total_amount = self.total_amount + amount;
+        match position {
+            Position::UP => self.up_amount = self.up_amount + amount,
+            Position::DOWN => self.down_amount = self.down_amount + amount,
+            Position::DRAW => self.draw_amount = self.draw_amount + amount,
+            Position::BUCKET(bucket) => {
+                let index = *bucket as usize;
+                if self.bucket_amounts.len() <= index {
+                    self.bucket_amounts.resize(index + 1, Uint128(0));
+                }
+                self.bucket_amounts[index] = self.bucket_amounts[index] + amount;
+            }
+        }
+    }
+
+    pub fn sub_bet_amount(&mut self, position: &Position, amount: Uint128) -> StdResult<()> {
+        self.total_amount = (self.total_amount - amount)?;
+        match position {
+            Position::UP => self.up_amount = (self.up_amount - amount)?,
+            Position::DOWN => self.down_amount = (self.down_amount - amount)?,
+            Position::DRAW => self.draw_amount = (self.draw_amount - amount)?,
+            Position::BUCKET(bucket) => {
+                let index = *bucket as usize;
+                self.bucket_amounts[index] = (self.bucket_amount(*bucket) - amount)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Round is either closed or cancelled
+    pub fn settled(&self) -> bool {
+        self.cancelled || self.close_price.is_some()
+    }
+
+    /// Status of round by open and close price
+    pub fn status(&self) -> RoundStatus {
+        if self.cancelled {
+            return RoundStatus::Cancelled;
+        }
+        match (self.open_price, self.close_price) {
+            (None, _) => RoundStatus::Betting,
+            (Some(_), None) => RoundStatus::Locked,
+            (Some(_), Some(_)) => {
+                if self.is_one_sided() {
+                    RoundStatus::OneSided
+                } else if self.draw_refunded() {
+                    RoundStatus::Refund
+                } else if self.has_no_winner() {
+                    RoundStatus::NoWinner
+                } else {
+                    RoundStatus::Closed
+                }
+            }
+        }
+    }
+
+    /// Result of bet by open and close price, rounds which are not closed are pending
+    pub fn bet_result(&self, user_bet: &Bet) -> BetResult {
+        if self.cancelled {
+            return BetResult::Refund;
+        }
+        match (self.open_price, self.close_price) {
+            (Some(_), Some(_)) => {
+                if self.refunded() {
+                    BetResult::Refund
+                } else if self.is_winner(&user_bet.position) {
+                    BetResult::Won
+                } else {
+                    BetResult::Lost
+                }
+            }
+            _ => BetResult::Pending,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -119,6 +512,49 @@ pub struct Bet {
     pub claimed: bool,
 }
 
+/// Performance of user in a market, bets are counted once their rounds are settled
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct UserStats {
+    pub rounds_played: u64,
+    pub total_wagered: Uint128,
+    pub total_won: Uint128,
+    pub current_streak: u64,
+    pub best_streak: u64,
+    /// Number of user bets counted, in order of user bet index
+    pub settled_bet_count: u64,
+}
+
+impl UserStats {
+    pub fn metric_value(&self, metric: &LeaderboardMetric) -> Uint128 {
+        match metric {
+            LeaderboardMetric::RoundsPlayed => Uint128(self.rounds_played as u128),
+            LeaderboardMetric::TotalWagered => self.total_wagered,
+            LeaderboardMetric::TotalWon => self.total_won,
+            LeaderboardMetric::NetProfit => {
+                (self.total_won - self.total_wagered).unwrap_or(Uint128(0))
+            }
+            LeaderboardMetric::BestStreak => Uint128(self.best_streak as u128),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntry {
+    pub user: CanonicalAddr,
+    pub value: Uint128,
+}
+
+/// Referral rewards of referrer in a market
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Referral {
+    pub referred_amount: Uint128,
+    pub claimed_amount: Uint128,
+    /// Number of rounds which referred users bet in
+    pub epoch_count: u64,
+    /// Number of rounds which rewards are claimed
+    pub claimed_epoch_count: u64,
+}
+
 pub fn store_config<S: Storage>(storage: &mut S, data: &Config) -> StdResult<()> {
     Singleton::new(storage, KEY_CONFIG).save(data)
 }
@@ -126,33 +562,1026 @@ pub fn read_config<S: Storage>(storage: &S) -> StdResult<Config> {
     ReadonlySingleton::new(storage, KEY_CONFIG).load()
 }
 
-pub fn store_state<S: Storage>(storage: &mut S, data: &State) -> StdResult<()> {
-    Singleton::new(storage, KEY_STATE).save(data)
+pub fn store_market<S: Storage>(storage: &mut S, market_id: u64, data: &Market) -> StdResult<()> {
+    Bucket::new(PREFIX_MARKET, storage).save(&market_id.to_be_bytes(), data)
 }
-pub fn read_state<S: Storage>(storage: &S) -> StdResult<State> {
-    ReadonlySingleton::new(storage, KEY_STATE).load()
+pub fn read_market<S: Storage>(storage: &S, market_id: u64) -> StdResult<Market> {
+    ReadonlyBucket::new(PREFIX_MARKET, storage).load(&market_id.to_be_bytes())
 }
 
-pub fn store_round<S: Storage>(storage: &mut S, epoch: Uint128, data: &Round) -> StdResult<()> {
-    Bucket::new(PREFIX_ROUND, storage).save(&epoch.u128().to_be_bytes(), data)
+pub fn store_state<S: Storage>(storage: &mut S, market_id: u64, data: &State) -> StdResult<()> {
+    Bucket::new(PREFIX_STATE, storage).save(&market_id.to_be_bytes(), data)
 }
-pub fn read_round<S: Storage>(storage: &S, epoch: Uint128) -> StdResult<Round> {
-    ReadonlyBucket::new(PREFIX_ROUND, storage).load(&epoch.u128().to_be_bytes())
+pub fn read_state<S: Storage>(storage: &S, market_id: u64) -> StdResult<State> {
+    ReadonlyBucket::new(PREFIX_STATE, storage).load(&market_id.to_be_bytes())
+}
+
+pub fn store_round<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    data: &Round,
+) -> StdResult<()> {
+    Bucket::multilevel(&[PREFIX_ROUND, &market_id.to_be_bytes()], storage)
+        .save(&epoch.u128().to_be_bytes(), data)
+}
+pub fn read_round<S: Storage>(storage: &S, market_id: u64, epoch: Uint128) -> StdResult<Round> {
+    ReadonlyBucket::multilevel(&[PREFIX_ROUND, &market_id.to_be_bytes()], storage)
+        .load(&epoch.u128().to_be_bytes())
+}
+pub fn may_read_round<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+) -> StdResult<Option<Round>> {
+    ReadonlyBucket::multilevel(&[PREFIX_ROUND, &market_id.to_be_bytes()], storage)
+        .may_load(&epoch.u128().to_be_bytes())
 }
 
 pub fn store_bet<S: Storage>(
     storage: &mut S,
+    market_id: u64,
     epoch: Uint128,
     user: CanonicalAddr,
     data: &Bet,
 ) -> StdResult<()> {
-    Bucket::new(PREFIX_ROUND, storage).save(
-        &[user.as_slice(), &epoch.u128().to_be_bytes()].concat(),
-        data,
+    let existing = may_read_bet(storage, market_id, epoch, user.clone())?;
+
+    Bucket::multilevel(
+        &[
+            PREFIX_BET,
+            &market_id.to_be_bytes(),
+            &epoch.u128().to_be_bytes(),
+        ],
+        storage,
+    )
+    .save(user.as_slice(), data)?;
+
+    if existing.is_none() {
+        index_user_bet(storage, market_id, &user, epoch)?;
+        index_round_bet(storage, market_id, epoch, &user)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_bet<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+    user: CanonicalAddr,
+) -> StdResult<Bet> {
+    ReadonlyBucket::multilevel(
+        &[
+            PREFIX_BET,
+            &market_id.to_be_bytes(),
+            &epoch.u128().to_be_bytes(),
+        ],
+        storage,
+    )
+    .load(user.as_slice())
+}
+
+pub fn may_read_bet<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+    user: CanonicalAddr,
+) -> StdResult<Option<Bet>> {
+    ReadonlyBucket::multilevel(
+        &[
+            PREFIX_BET,
+            &market_id.to_be_bytes(),
+            &epoch.u128().to_be_bytes(),
+        ],
+        storage,
+    )
+    .may_load(user.as_slice())
+}
+
+/// Index is kept sorted by epoch, so user bets can be paginated by epoch
+fn index_user_bet<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    user: &CanonicalAddr,
+    epoch: Uint128,
+) -> StdResult<()> {
+    let count = read_user_bet_count(storage, market_id, user)?;
+
+    let mut bucket: Bucket<S, Uint128> = Bucket::multilevel(
+        &[PREFIX_USER_BET, &market_id.to_be_bytes(), user.as_slice()],
+        storage,
+    );
+    let mut index = count;
+    while index > 0 {
+        let prev_epoch = bucket.load(&(index - 1).to_be_bytes())?;
+        if prev_epoch < epoch {
+            break;
+        }
+        bucket.save(&index.to_be_bytes(), &prev_epoch)?;
+        index -= 1;
+    }
+    bucket.save(&index.to_be_bytes(), &epoch)?;
+
+    Bucket::multilevel(&[PREFIX_USER_BET_COUNT, &market_id.to_be_bytes()], storage)
+        .save(user.as_slice(), &(count + 1))
+}
+
+pub fn read_user_bet_count<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    user: &CanonicalAddr,
+) -> StdResult<u64> {
+    Ok(
+        ReadonlyBucket::multilevel(&[PREFIX_USER_BET_COUNT, &market_id.to_be_bytes()], storage)
+            .may_load(user.as_slice())?
+            .unwrap_or(0),
+    )
+}
+
+pub fn read_user_bet_epoch<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    user: &CanonicalAddr,
+    index: u64,
+) -> StdResult<Uint128> {
+    ReadonlyBucket::multilevel(
+        &[PREFIX_USER_BET, &market_id.to_be_bytes(), user.as_slice()],
+        storage,
+    )
+    .load(&index.to_be_bytes())
+}
+
+/// User bets before the index are claimed or have nothing to claim, in order of user bet index
+pub fn store_claim_index<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    user: &CanonicalAddr,
+    index: u64,
+) -> StdResult<()> {
+    Bucket::multilevel(&[PREFIX_CLAIM_INDEX, &market_id.to_be_bytes()], storage)
+        .save(user.as_slice(), &index)
+}
+
+pub fn read_claim_index<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    user: &CanonicalAddr,
+) -> StdResult<u64> {
+    Ok(
+        ReadonlyBucket::multilevel(&[PREFIX_CLAIM_INDEX, &market_id.to_be_bytes()], storage)
+            .may_load(user.as_slice())?
+            .unwrap_or(0),
     )
 }
 
-pub fn read_bet<S: Storage>(storage: &S, epoch: Uint128, user: CanonicalAddr) -> StdResult<Bet> {
+/// Number of user bets placed before the epoch
+pub fn count_user_bets_before<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    user: &CanonicalAddr,
+    epoch: Uint128,
+) -> StdResult<u64> {
+    let mut low: u64 = 0;
+    let mut high = read_user_bet_count(storage, market_id, user)?;
+    while low < high {
+        let mid = (low + high) / 2;
+        if read_user_bet_epoch(storage, market_id, user, mid)? < epoch {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+fn index_round_bet<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    user: &CanonicalAddr,
+) -> StdResult<()> {
+    let count = read_round_bet_count(storage, market_id, epoch)?;
+    let epoch_key = epoch.u128().to_be_bytes();
+
+    Bucket::multilevel(
+        &[PREFIX_ROUND_BET, &market_id.to_be_bytes(), &epoch_key],
+        storage,
+    )
+    .save(&count.to_be_bytes(), user)?;
+    Bucket::multilevel(&[PREFIX_ROUND_BET_COUNT, &market_id.to_be_bytes()], storage)
+        .save(&epoch_key, &(count + 1))
+}
+
+pub fn read_round_bet_count<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+) -> StdResult<u64> {
+    Ok(
+        ReadonlyBucket::multilevel(&[PREFIX_ROUND_BET_COUNT, &market_id.to_be_bytes()], storage)
+            .may_load(&epoch.u128().to_be_bytes())?
+            .unwrap_or(0),
+    )
+}
+
+pub fn read_round_bettor<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+    index: u64,
+) -> StdResult<CanonicalAddr> {
+    ReadonlyBucket::multilevel(
+        &[
+            PREFIX_ROUND_BET,
+            &market_id.to_be_bytes(),
+            &epoch.u128().to_be_bytes(),
+        ],
+        storage,
+    )
+    .load(&index.to_be_bytes())
+}
+
+pub fn store_referrer<S: Storage>(
+    storage: &mut S,
+    user: &CanonicalAddr,
+    referrer: &CanonicalAddr,
+) -> StdResult<()> {
+    Bucket::new(PREFIX_REFERRER, storage).save(user.as_slice(), referrer)?;
+
+    let referred_users = read_referred_users(storage, referrer)?;
+    Bucket::new(PREFIX_REFERRED_USERS, storage).save(referrer.as_slice(), &(referred_users + 1))
+}
+pub fn read_referrer<S: Storage>(
+    storage: &S,
+    user: &CanonicalAddr,
+) -> StdResult<Option<CanonicalAddr>> {
+    ReadonlyBucket::new(PREFIX_REFERRER, storage).may_load(user.as_slice())
+}
+
+/// Addresses allowed to claim on behalf of user in all markets
+pub fn store_claimers<S: Storage>(
+    storage: &mut S,
+    user: &CanonicalAddr,
+    claimers: &[CanonicalAddr],
+) -> StdResult<()> {
+    Bucket::new(PREFIX_CLAIMERS, storage).save(user.as_slice(), &claimers.to_vec())
+}
+pub fn read_claimers<S: Storage>(
+    storage: &S,
+    user: &CanonicalAddr,
+) -> StdResult<Vec<CanonicalAddr>> {
+    Ok(ReadonlyBucket::new(PREFIX_CLAIMERS, storage)
+        .may_load(user.as_slice())?
+        .unwrap_or_default())
+}
+
+/// Viewing key of the contract on token, set once for all markets betting the token
+pub fn store_viewing_key<S: Storage>(
+    storage: &mut S,
+    token: &CanonicalAddr,
+    viewing_key: &str,
+) -> StdResult<()> {
+    Bucket::new(PREFIX_VIEWING_KEY, storage).save(token.as_slice(), &viewing_key.to_string())
+}
+pub fn read_viewing_key<S: Storage>(
+    storage: &S,
+    token: &CanonicalAddr,
+) -> StdResult<Option<String>> {
+    ReadonlyBucket::new(PREFIX_VIEWING_KEY, storage).may_load(token.as_slice())
+}
+
+pub fn read_referred_users<S: Storage>(storage: &S, referrer: &CanonicalAddr) -> StdResult<u64> {
+    Ok(ReadonlyBucket::new(PREFIX_REFERRED_USERS, storage)
+        .may_load(referrer.as_slice())?
+        .unwrap_or(0))
+}
+
+pub fn store_referral<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    referrer: &CanonicalAddr,
+    data: &Referral,
+) -> StdResult<()> {
+    Bucket::multilevel(&[PREFIX_REFERRAL, &market_id.to_be_bytes()], storage)
+        .save(referrer.as_slice(), data)
+}
+pub fn read_referral<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    referrer: &CanonicalAddr,
+) -> StdResult<Referral> {
+    Ok(
+        ReadonlyBucket::multilevel(&[PREFIX_REFERRAL, &market_id.to_be_bytes()], storage)
+            .may_load(referrer.as_slice())?
+            .unwrap_or_default(),
+    )
+}
+
+/// Add referred bet amount of the round, rounds are indexed in order of epoch
+pub fn add_referral_bet<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    referrer: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut referral = read_referral(storage, market_id, referrer)?;
+    let referred_amount = read_referral_bet(storage, market_id, epoch, referrer)?;
+
+    if !referral_round_indexed(storage, market_id, epoch, referrer)? {
+        Bucket::multilevel(
+            &[
+                PREFIX_REFERRAL_EPOCH,
+                &market_id.to_be_bytes(),
+                referrer.as_slice(),
+            ],
+            storage,
+        )
+        .save(&referral.epoch_count.to_be_bytes(), &epoch)?;
+        referral.epoch_count += 1;
+    }
+    referral.referred_amount = referral.referred_amount + amount;
+
+    store_referral_bet(
+        storage,
+        market_id,
+        epoch,
+        referrer,
+        referred_amount + amount,
+    )?;
+    store_referral(storage, market_id, referrer, &referral)
+}
+
+/// Subtract referred bet amount of the round, returns subtracted amount
+pub fn sub_referral_bet<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    referrer: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let mut referral = read_referral(storage, market_id, referrer)?;
+    let referred_amount = read_referral_bet(storage, market_id, epoch, referrer)?;
+
+    // referrer could be set after the user bet in the round
+    let amount = amount.min(referred_amount);
+    referral.referred_amount = (referral.referred_amount - amount)?;
+
+    store_referral_bet(
+        storage,
+        market_id,
+        epoch,
+        referrer,
+        (referred_amount - amount)?,
+    )?;
+    store_referral(storage, market_id, referrer, &referral)?;
+
+    Ok(amount)
+}
+
+fn referral_round_indexed<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+    referrer: &CanonicalAddr,
+) -> StdResult<bool> {
+    let referral = read_referral(storage, market_id, referrer)?;
+    Ok(referral.epoch_count > 0
+        && read_referral_epoch(storage, market_id, referrer, referral.epoch_count - 1)? == epoch)
+}
+
+fn store_referral_bet<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    referrer: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    Bucket::multilevel(
+        &[
+            PREFIX_REFERRAL_BET,
+            &market_id.to_be_bytes(),
+            &epoch.u128().to_be_bytes(),
+        ],
+        storage,
+    )
+    .save(referrer.as_slice(), &amount)
+}
+
+pub fn read_referral_bet<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+    referrer: &CanonicalAddr,
+) -> StdResult<Uint128> {
+    Ok(ReadonlyBucket::multilevel(
+        &[
+            PREFIX_REFERRAL_BET,
+            &market_id.to_be_bytes(),
+            &epoch.u128().to_be_bytes(),
+        ],
+        storage,
+    )
+    .may_load(referrer.as_slice())?
+    .unwrap_or(Uint128(0)))
+}
+
+pub fn read_referral_epoch<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    referrer: &CanonicalAddr,
+    index: u64,
+) -> StdResult<Uint128> {
+    ReadonlyBucket::multilevel(
+        &[
+            PREFIX_REFERRAL_EPOCH,
+            &market_id.to_be_bytes(),
+            referrer.as_slice(),
+        ],
+        storage,
+    )
+    .load(&index.to_be_bytes())
+}
+
+/// Settled rounds of referrer whose rewards are not claimed yet, up to limit rounds,
+/// with referred bet amount of each round
+pub fn read_referral_rounds<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    referrer: &CanonicalAddr,
+    referral: &Referral,
+    limit: u64,
+) -> StdResult<Vec<(Uint128, Round, Uint128)>> {
+    let mut rounds = vec![];
+    let mut index = referral.claimed_epoch_count;
+    while index < referral.epoch_count && (rounds.len() as u64) < limit {
+        let epoch = read_referral_epoch(storage, market_id, referrer, index)?;
+        let round = read_round(storage, market_id, epoch)?;
+        if !round.settled() {
+            break;
+        }
+
+        let referred_amount = read_referral_bet(storage, market_id, epoch, referrer)?;
+        rounds.push((epoch, round, referred_amount));
+        index += 1;
+    }
+
+    Ok(rounds)
+}
+
+/// Number of bettors of round processed by auto payout, in order of round bet index
+pub fn store_distributed_count<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    epoch: Uint128,
+    count: u64,
+) -> StdResult<()> {
+    Bucket::multilevel(
+        &[PREFIX_DISTRIBUTED_COUNT, &market_id.to_be_bytes()],
+        storage,
+    )
+    .save(&epoch.u128().to_be_bytes(), &count)
+}
+pub fn read_distributed_count<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    epoch: Uint128,
+) -> StdResult<u64> {
+    Ok(ReadonlyBucket::multilevel(
+        &[PREFIX_DISTRIBUTED_COUNT, &market_id.to_be_bytes()],
+        storage,
+    )
+    .may_load(&epoch.u128().to_be_bytes())?
+    .unwrap_or(0))
+}
+
+pub fn store_user_stats<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    user: &CanonicalAddr,
+    data: &UserStats,
+) -> StdResult<()> {
+    Bucket::multilevel(&[PREFIX_USER_STATS, &market_id.to_be_bytes()], storage)
+        .save(user.as_slice(), data)
+}
+pub fn read_user_stats<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    user: &CanonicalAddr,
+) -> StdResult<UserStats> {
+    Ok(
+        ReadonlyBucket::multilevel(&[PREFIX_USER_STATS, &market_id.to_be_bytes()], storage)
+            .may_load(user.as_slice())?
+            .unwrap_or_default(),
+    )
+}
+
+fn leaderboard_key(metric: &LeaderboardMetric) -> &[u8] {
+    match metric {
+        LeaderboardMetric::RoundsPlayed => b"rounds_played",
+        LeaderboardMetric::TotalWagered => b"total_wagered",
+        LeaderboardMetric::TotalWon => b"total_won",
+        LeaderboardMetric::NetProfit => b"net_profit",
+        LeaderboardMetric::BestStreak => b"best_streak",
+    }
+}
+
+/// Leaderboard is sorted by value in descending order
+pub fn read_leaderboard<S: Storage>(
+    storage: &S,
+    market_id: u64,
+    metric: &LeaderboardMetric,
+) -> StdResult<Vec<LeaderboardEntry>> {
+    Ok(
+        ReadonlyBucket::multilevel(&[PREFIX_LEADERBOARD, &market_id.to_be_bytes()], storage)
+            .may_load(leaderboard_key(metric))?
+            .unwrap_or_default(),
+    )
+}
+
+/// Move user to the position of the value, users with same value keep their order
+pub fn update_leaderboard<S: Storage>(
+    storage: &mut S,
+    market_id: u64,
+    metric: &LeaderboardMetric,
+    user: &CanonicalAddr,
+    value: Uint128,
+) -> StdResult<()> {
+    let mut entries = read_leaderboard(storage, market_id, metric)?;
+    entries.retain(|entry| entry.user != *user);
+
+    if !value.is_zero() {
+        let index = entries
+            .iter()
+            .position(|entry| entry.value < value)
+            .unwrap_or(entries.len());
+        entries.insert(
+            index,
+            LeaderboardEntry {
+                user: user.clone(),
+                value,
+            },
+        );
+        entries.truncate(LEADERBOARD_SIZE);
+    }
+
+    Bucket::multilevel(&[PREFIX_LEADERBOARD, &market_id.to_be_bytes()], storage)
+        .save(leaderboard_key(metric), &entries)
+}
+
+/// Config of the single market contract, stored under the same key as `Config`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub owner_addr: CanonicalAddr,
+    pub operator_addr: CanonicalAddr,
+    pub treasury_addr: CanonicalAddr,
+    pub bet_asset: AssetInfoRaw,
+    pub oracle_addr: CanonicalAddr,
+    pub oracle_code_hash: String,
+    pub fee_rate: Decimal,
+    pub interval: u64,
+    pub grace_interval: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyState {
+    pub epoch: Uint128,
+    pub total_fee: Uint128,
+    pub paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyRound {
+    pub start_time: u64,
+    pub lock_time: u64,
+    pub end_time: u64,
+    pub open_price: Option<Uint128>,
+    pub close_price: Option<Uint128>,
+    pub total_amount: Uint128,
+    pub reward_amount: Uint128,
+    pub up_amount: Uint128,
+    pub down_amount: Uint128,
+    pub is_genesis: bool,
+}
+
+/// Progress of moving storage of the single market contract into the legacy market
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyMigration {
+    /// State of the legacy market, stored once all rounds are moved
+    pub state: State,
+    /// Rounds up to this epoch are moved
+    pub migrated_epoch: Uint128,
+}
+
+impl LegacyMigration {
+    /// Convert legacy round and count its liabilities in state of the market
+    pub fn add_round(&mut self, legacy_round: LegacyRound) -> StdResult<Round> {
+        let round = Round {
+            start_time: legacy_round.start_time,
+            lock_time: legacy_round.lock_time,
+            end_time: legacy_round.end_time,
+            open_price: legacy_round.open_price,
+            close_price: legacy_round.close_price,
+            total_amount: legacy_round.total_amount,
+            reward_amount: legacy_round.reward_amount,
+            up_amount: legacy_round.up_amount,
+            down_amount: legacy_round.down_amount,
+            draw_amount: Uint128(0),
+            bucket_amounts: vec![],
+            winning_bucket: None,
+            referred_amount: Uint128(0),
+            referral_fee: Uint128(0),
+            referral_paid: Uint128(0),
+            referral_paid_amount: Uint128(0),
+            // rounds closed with unchanged price were refunded
+            draw_policy: DrawPolicy::Refund,
+            is_genesis: legacy_round.is_genesis,
+            cancelled: false,
+            executor: None,
+            bounty: Uint128(0),
+            paid_out: Uint128(0),
+            paid_bet_amount: Uint128(0),
+        };
+
+        let state = &mut self.state;
+        if !round.settled() {
+            state.open_pool = state.open_pool + round.total_amount;
+        } else if round.refunded() {
+            // fee was taken from one-sided rounds, which are refunded in full now.
+            // Fee already withdrawn cannot be moved back and is shown by solvency query
+            if !round.is_draw() {
+                let fee = (round.total_amount - round.reward_amount)?;
+                state.total_fee = (state.total_fee - fee.min(state.total_fee))?;
+            }
+            state.unclaimed_amount = state.unclaimed_amount + round.total_amount;
+        } else {
+            state.unclaimed_amount = state.unclaimed_amount + round.reward_amount;
+        }
+
+        Ok(round)
+    }
+}
+
+pub fn read_legacy_config<S: Storage>(storage: &S) -> StdResult<LegacyConfig> {
+    ReadonlySingleton::new(storage, KEY_CONFIG).load()
+}
+
+pub fn read_legacy_state<S: Storage>(storage: &S) -> StdResult<LegacyState> {
+    ReadonlySingleton::new(storage, KEY_LEGACY_STATE).load()
+}
+pub fn remove_legacy_state<S: Storage>(storage: &mut S) {
+    Singleton::<S, LegacyState>::new(storage, KEY_LEGACY_STATE).remove()
+}
+
+pub fn read_legacy_round<S: Storage>(
+    storage: &S,
+    epoch: Uint128,
+) -> StdResult<Option<LegacyRound>> {
+    ReadonlyBucket::new(PREFIX_ROUND, storage).may_load(&epoch.u128().to_be_bytes())
+}
+pub fn remove_legacy_round<S: Storage>(storage: &mut S, epoch: Uint128) {
+    let mut bucket: Bucket<S, LegacyRound> = Bucket::new(PREFIX_ROUND, storage);
+    bucket.remove(&epoch.u128().to_be_bytes());
+}
+
+/// Bets used to be stored in the round bucket keyed by `user ++ epoch`
+pub fn read_legacy_bet<S: Storage>(
+    storage: &S,
+    epoch: Uint128,
+    user: &CanonicalAddr,
+) -> StdResult<Option<Bet>> {
     ReadonlyBucket::new(PREFIX_ROUND, storage)
-        .load(&[user.as_slice(), &epoch.u128().to_be_bytes()].concat())
+        .may_load(&[user.as_slice(), &epoch.u128().to_be_bytes()].concat())
+}
+pub fn remove_legacy_bet<S: Storage>(storage: &mut S, epoch: Uint128, user: &CanonicalAddr) {
+    let mut bucket: Bucket<S, Bet> = Bucket::new(PREFIX_ROUND, storage);
+    bucket.remove(&[user.as_slice(), &epoch.u128().to_be_bytes()].concat());
+}
+
+pub fn store_legacy_migration<S: Storage>(
+    storage: &mut S,
+    data: &LegacyMigration,
+) -> StdResult<()> {
+    Singleton::new(storage, KEY_LEGACY_MIGRATION).save(data)
+}
+pub fn read_legacy_migration<S: Storage>(storage: &S) -> StdResult<Option<LegacyMigration>> {
+    ReadonlySingleton::new(storage, KEY_LEGACY_MIGRATION).may_load()
+}
+pub fn remove_legacy_migration<S: Storage>(storage: &mut S) {
+    Singleton::<S, LegacyMigration>::new(storage, KEY_LEGACY_MIGRATION).remove()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_env;
+
+    /// UP round closed with price increase, with winning bets and a losing bet
+    fn closed_round(win_bets: &[u128], lose_bet: u128, fee: u128) -> Round {
+        let up_amount: u128 = win_bets.iter().sum();
+        let total_amount = up_amount + lose_bet;
+        Round {
+            start_time: 0,
+            lock_time: 100,
+            end_time: 200,
+            open_price: Some(Uint128(100)),
+            close_price: Some(Uint128(101)),
+            total_amount: Uint128(total_amount),
+            reward_amount: Uint128(total_amount - fee),
+            up_amount: Uint128(up_amount),
+            down_amount: Uint128(lose_bet),
+            draw_amount: Uint128(0),
+            bucket_amounts: vec![],
+            winning_bucket: None,
+            referred_amount: Uint128(0),
+            referral_fee: Uint128(0),
+            referral_paid: Uint128(0),
+            referral_paid_amount: Uint128(0),
+            draw_policy: DrawPolicy::Refund,
+            is_genesis: false,
+            cancelled: false,
+            executor: None,
+            bounty: Uint128(0),
+            paid_out: Uint128(0),
+            paid_bet_amount: Uint128(0),
+        }
+    }
+
+    fn bet(amount: u128, position: Position) -> Bet {
+        Bet {
+            amount: Uint128(amount),
+            position,
+            claimed: false,
+        }
+    }
+
+    #[test]
+    fn payouts_never_exceed_reward() {
+        let cases: Vec<(Vec<u128>, u128, u128)> = vec![
+            (vec![1, 1, 1], 8, 1),
+            (vec![7, 11, 13, 17], 1_000_003, 30_001),
+            (vec![333_333, 333_333, 333_334], 2, 0),
+            (vec![1], 999_999_999, 3),
+            (
+                vec![1_000_000_000_000_000, 3, 5],
+                999_999_999_999_999,
+                12_345,
+            ),
+        ];
+
+        for (win_bets, lose_bet, fee) in cases {
+            let mut round = closed_round(&win_bets, lose_bet, fee);
+            let mut total_paid = Uint128(0);
+            let mut total_dust = Uint128(0);
+            for amount in win_bets.iter() {
+                let user_bet = bet(*amount, Position::UP);
+                let reward = round.reward_of(&user_bet);
+                assert!(reward >= user_bet.amount);
+
+                total_paid = total_paid + reward;
+                total_dust = total_dust + round.record_payout(&user_bet, reward).unwrap();
+                assert!(total_paid <= round.reward_amount);
+            }
+
+            assert_eq!(total_paid + total_dust, round.reward_amount);
+            assert_eq!(round.paid_out, round.reward_amount);
+        }
+    }
+
+    #[test]
+    fn empty_winning_bucket_is_refunded() {
+        let mut round = closed_round(&[], 0, 0);
+        round.up_amount = Uint128(0);
+        round.bucket_amounts = vec![Uint128(300), Uint128(700)];
+        round.total_amount = Uint128(1000);
+        round.reward_amount = Uint128(0);
+        round.winning_bucket = Some(3);
+
+        assert!(round.has_no_winner());
+        assert!(round.refunded());
+        assert_eq!(round.status(), RoundStatus::NoWinner);
+
+        let mut env = mock_env("user", &[]);
+        env.block.time = round.end_time;
+        let user_bet = bet(300, Position::BUCKET(0));
+        assert!(!round.claimable(env.clone()));
+        assert!(round.refundable(env.clone(), 0));
+        assert_eq!(
+            round.claimable_amount(env, user_bet.clone(), 0),
+            Uint128(300)
+        );
+        assert_eq!(round.bet_result(&user_bet), BetResult::Refund);
+
+        // refunds never sweep dust
+        assert_eq!(
+            round.record_payout(&user_bet, Uint128(300)).unwrap(),
+            Uint128(0)
+        );
+
+        round.winning_bucket = Some(1);
+        assert!(!round.has_no_winner());
+        assert_eq!(round.status(), RoundStatus::Closed);
+    }
+
+    #[test]
+    fn empty_draw_pool_is_refunded() {
+        let mut env = mock_env("user", &[]);
+        env.block.time = 200;
+
+        // unchanged price without DRAW bets
+        let mut round = closed_round(&[500], 500, 10);
+        round.draw_policy = DrawPolicy::Draw;
+        round.close_price = round.open_price;
+        assert!(round.has_no_winner());
+        assert_eq!(round.status(), RoundStatus::NoWinner);
+        assert!(!round.claimable(env.clone()));
+        assert_eq!(
+            round.claimable_amount(env.clone(), bet(500, Position::DOWN), 0),
+            Uint128(500)
+        );
+
+        // falling price without DOWN bets
+        let mut round = closed_round(&[500], 0, 10);
+        round.draw_policy = DrawPolicy::Draw;
+        round.draw_amount = Uint128(500);
+        round.total_amount = Uint128(1000);
+        round.close_price = Some(Uint128(99));
+        assert!(round.has_no_winner());
+        assert_eq!(
+            round.claimable_amount(env.clone(), bet(500, Position::DRAW), 0),
+            Uint128(500)
+        );
+
+        // draw is still taken by house with house wins policy
+        let mut round = closed_round(&[500], 500, 10);
+        round.draw_policy = DrawPolicy::HouseWins;
+        round.close_price = round.open_price;
+        assert!(!round.has_no_winner());
+        assert!(!round.refunded());
+    }
+
+    #[test]
+    fn dust_is_counted_once() {
+        let mut round = closed_round(&[1, 1, 1], 8, 1);
+
+        let first = bet(1, Position::UP);
+        assert_eq!(round.reward_of(&first), Uint128(3));
+        assert_eq!(round.record_payout(&first, Uint128(3)).unwrap(), Uint128(0));
+
+        let losing = bet(8, Position::DOWN);
+        assert_eq!(round.reward_of(&losing), Uint128(0));
+        assert_eq!(
+            round.record_payout(&losing, Uint128(0)).unwrap(),
+            Uint128(0)
+        );
+
+        let second = bet(1, Position::UP);
+        assert_eq!(
+            round.record_payout(&second, Uint128(3)).unwrap(),
+            Uint128(0)
+        );
+        let last = bet(1, Position::UP);
+        assert_eq!(round.record_payout(&last, Uint128(3)).unwrap(), Uint128(1));
+
+        // cancelled bet kept with zero amount does not sweep dust again
+        let cancelled = bet(0, Position::UP);
+        assert_eq!(
+            round.record_payout(&cancelled, Uint128(0)).unwrap(),
+            Uint128(0)
+        );
+        assert_eq!(round.paid_out, Uint128(10));
+    }
+
+    #[test]
+    fn referral_dust_is_swept_by_last_referrer() {
+        let mut round = closed_round(&[500], 500, 30);
+        round.referred_amount = Uint128(300);
+        round.referral_fee = Uint128(10);
+
+        let referred = Uint128(100);
+        assert_eq!(round.referral_reward_of(referred), Uint128(3));
+        assert_eq!(
+            round.record_referral_payout(referred, Uint128(3)).unwrap(),
+            Uint128(0)
+        );
+
+        // referrer whose referred bet was cancelled
+        assert_eq!(
+            round
+                .record_referral_payout(Uint128(0), Uint128(0))
+                .unwrap(),
+            Uint128(0)
+        );
+
+        assert_eq!(
+            round.record_referral_payout(referred, Uint128(3)).unwrap(),
+            Uint128(0)
+        );
+        assert_eq!(
+            round.record_referral_payout(referred, Uint128(3)).unwrap(),
+            Uint128(1)
+        );
+        assert_eq!(round.referral_paid, round.referral_fee);
+    }
+
+    #[test]
+    fn rewards_of_18_decimal_amounts() {
+        const TOKEN: u128 = 1_000_000_000_000_000_000;
+
+        // 100 token pool with 20 token bet, whose product exceeds 128 bits
+        let mut round = closed_round(&[20 * TOKEN, 30 * TOKEN], 50 * TOKEN, 3 * TOKEN);
+        let user_bet = bet(20 * TOKEN, Position::UP);
+        assert_eq!(
+            round.reward_of(&user_bet),
+            Uint128(38_800_000_000_000_000_000)
+        );
+        assert_eq!(
+            round
+                .record_payout(&user_bet, round.reward_of(&user_bet))
+                .unwrap(),
+            Uint128(0)
+        );
+
+        // fee of million token pool
+        let pool = Uint128(1_000_000 * TOKEN);
+        assert_eq!(
+            decimal_mul(pool, Decimal::percent(3)),
+            Uint128(30_000 * TOKEN)
+        );
+
+        // referral fee of referred bets
+        assert_eq!(
+            multiply_ratio(
+                Uint128(3 * TOKEN),
+                Uint128(20 * TOKEN),
+                Uint128(100 * TOKEN)
+            ),
+            Uint128(600_000_000_000_000_000)
+        );
+        assert_eq!(
+            multiply_ratio(
+                Uint128(u128::MAX),
+                Uint128(u128::MAX - 1),
+                Uint128(u128::MAX)
+            ),
+            Uint128(u128::MAX - 1)
+        );
+    }
+
+    #[test]
+    fn legacy_rounds_count_liabilities() {
+        let legacy_round =
+            |open: u128, close: Option<u128>, up: u128, down: u128, fee: u128| LegacyRound {
+                start_time: 0,
+                lock_time: 100,
+                end_time: 200,
+                open_price: Some(Uint128(open)),
+                close_price: close.map(Uint128),
+                total_amount: Uint128(up + down),
+                reward_amount: Uint128(if close.is_some() { up + down - fee } else { 0 }),
+                up_amount: Uint128(up),
+                down_amount: Uint128(down),
+                is_genesis: false,
+            };
+        let mut migration = LegacyMigration {
+            state: State {
+                epoch: Uint128(4),
+                total_fee: Uint128(50),
+                paused: true,
+                open_pool: Uint128(0),
+                unclaimed_amount: Uint128(0),
+                referral_rewards: Uint128(0),
+                emergency: false,
+                emergency_time: 0,
+            },
+            migrated_epoch: Uint128(0),
+        };
+
+        // closed round with winners
+        let round = migration
+            .add_round(legacy_round(100, Some(101), 600, 400, 30))
+            .unwrap();
+        assert_eq!(round.reward_of(&bet(600, Position::UP)), Uint128(970));
+
+        // draw was refunded without fee
+        let round = migration
+            .add_round(legacy_round(100, Some(100), 200, 100, 300))
+            .unwrap();
+        assert!(round.refunded());
+
+        // fee of one-sided round is moved back, as it is refunded in full now
+        migration
+            .add_round(legacy_round(100, Some(99), 500, 0, 20))
+            .unwrap();
+
+        // locked round which is not closed
+        migration
+            .add_round(legacy_round(100, None, 70, 80, 0))
+            .unwrap();
+
+        assert_eq!(migration.state.unclaimed_amount, Uint128(970 + 300 + 500));
+        assert_eq!(migration.state.open_pool, Uint128(150));
+        assert_eq!(migration.state.total_fee, Uint128(30));
+    }
 }
@@ -0,0 +1,645 @@
+use std::convert::TryFrom;
+
+use cosmwasm_std::{HumanAddr, LogAttribute, StdError, StdResult, Uint128};
+
+use scrt_prediction::event::{
+    AcceptOperatorEvent, AcceptOwnershipEvent, BetEvent, CancelBetEvent, ClaimEvent,
+    ClaimReferralRewardsEvent, ClaimerEvent, DistributeRoundEvent, EmergencyEvent, Event,
+    FeeAccrualEvent, FeeSource, MigrateBetsEvent, MigrateLegacyEvent, PauseEvent,
+    ProposeOperatorEvent, ProposeOwnerEvent, RegisterMarketEvent, RoundCancelEvent,
+    RoundCloseEvent, RoundLockEvent, RoundStartEvent, SwitchPositionEvent, UpdateConfigEvent,
+    UpdateMarketEvent, WithdrawEvent,
+};
+use scrt_prediction::prediction::Position;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PredictionEvent {
+    Bet(BetEvent),
+    CancelBet(CancelBetEvent),
+    SwitchPosition(SwitchPositionEvent),
+    Claim(ClaimEvent),
+    Claimer(ClaimerEvent),
+    ClaimReferralRewards(ClaimReferralRewardsEvent),
+    RoundStart(RoundStartEvent),
+    RoundLock(RoundLockEvent),
+    RoundClose(RoundCloseEvent),
+    RoundCancel(RoundCancelEvent),
+    DistributeRound(DistributeRoundEvent),
+    FeeAccrual(FeeAccrualEvent),
+    Withdraw(WithdrawEvent),
+    Pause(PauseEvent),
+    Emergency(EmergencyEvent),
+    UpdateConfig(UpdateConfigEvent),
+    ProposeOwner(ProposeOwnerEvent),
+    AcceptOwnership(AcceptOwnershipEvent),
+    ProposeOperator(ProposeOperatorEvent),
+    AcceptOperator(AcceptOperatorEvent),
+    RegisterMarket(RegisterMarketEvent),
+    UpdateMarket(UpdateMarketEvent),
+    MigrateLegacy(MigrateLegacyEvent),
+    MigrateBets(MigrateBetsEvent),
+}
+
+/// Parse logs of a `HandleResponse` into events, in the order they are logged
+pub fn parse_events(logs: &[LogAttribute]) -> StdResult<Vec<PredictionEvent>> {
+    let mut events = vec![];
+    let mut start: Option<usize> = None;
+    for (index, attribute) in logs.iter().enumerate() {
+        if attribute.key == "action" {
+            if let Some(start) = start {
+                events.push(parse_event(&logs[start..index])?);
+            }
+            start = Some(index);
+        } else if start.is_none() {
+            return Err(StdError::generic_err("Logs should start with action"));
+        }
+    }
+    if let Some(start) = start {
+        events.push(parse_event(&logs[start..])?);
+    }
+
+    Ok(events)
+}
+
+/// Parse logs of a single event, which start with `action`
+pub fn parse_event(logs: &[LogAttribute]) -> StdResult<PredictionEvent> {
+    let (action, attributes) = match logs.split_first() {
+        Some((action, attributes)) if action.key == "action" => {
+            (action.value.as_str(), Attributes(attributes))
+        }
+        _ => return Err(StdError::generic_err("Logs should start with action")),
+    };
+
+    let event = match action {
+        BetEvent::ACTION => PredictionEvent::Bet(BetEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            user: attributes.addr("user")?,
+            position: attributes.position("position")?,
+            amount: attributes.uint128("amount")?,
+            bet_amount: attributes.uint128("bet_amount")?,
+            referrer: attributes.may_get("referrer").map(HumanAddr::from),
+        }),
+        CancelBetEvent::ACTION => PredictionEvent::CancelBet(CancelBetEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            user: attributes.addr("user")?,
+            amount: attributes.uint128("amount")?,
+            penalty: attributes.uint128("penalty")?,
+        }),
+        SwitchPositionEvent::ACTION => PredictionEvent::SwitchPosition(SwitchPositionEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            user: attributes.addr("user")?,
+            position: attributes.position("position")?,
+            amount: attributes.uint128("amount")?,
+            penalty: attributes.uint128("penalty")?,
+        }),
+        ClaimEvent::ACTION => PredictionEvent::Claim(ClaimEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            user: attributes.addr("user")?,
+            recipient: attributes.addr("recipient")?,
+            amount: attributes.uint128("amount")?,
+        }),
+        ClaimerEvent::ACTION => PredictionEvent::Claimer(ClaimerEvent {
+            user: attributes.addr("user")?,
+            claimer: attributes.addr("claimer")?,
+            allowed: attributes.bool("allowed")?,
+        }),
+        ClaimReferralRewardsEvent::ACTION => {
+            PredictionEvent::ClaimReferralRewards(ClaimReferralRewardsEvent {
+                market_id: attributes.u64("market_id")?,
+                referrer: attributes.addr("referrer")?,
+                amount: attributes.uint128("amount")?,
+            })
+        }
+        RoundStartEvent::ACTION => PredictionEvent::RoundStart(RoundStartEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            start_time: attributes.u64("start_time")?,
+            lock_time: attributes.u64("lock_time")?,
+            end_time: attributes.u64("end_time")?,
+        }),
+        RoundLockEvent::ACTION => PredictionEvent::RoundLock(RoundLockEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            open_price: attributes.uint128("open_price")?,
+            total_amount: attributes.uint128("total_amount")?,
+        }),
+        RoundCloseEvent::ACTION => PredictionEvent::RoundClose(RoundCloseEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            close_price: attributes.uint128("close_price")?,
+            total_amount: attributes.uint128("total_amount")?,
+            reward_amount: attributes.uint128("reward_amount")?,
+            executor: attributes.addr("executor")?,
+            bounty: attributes.uint128("bounty")?,
+        }),
+        RoundCancelEvent::ACTION => PredictionEvent::RoundCancel(RoundCancelEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+        }),
+        DistributeRoundEvent::ACTION => PredictionEvent::DistributeRound(DistributeRoundEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            distributed_count: attributes.u64("distributed_count")?,
+            bettor_count: attributes.u64("bettor_count")?,
+        }),
+        FeeAccrualEvent::ACTION => PredictionEvent::FeeAccrual(FeeAccrualEvent {
+            market_id: attributes.u64("market_id")?,
+            epoch: attributes.uint128("epoch")?,
+            source: attributes.fee_source("source")?,
+            fee: attributes.uint128("fee")?,
+            referral_fee: attributes.uint128("referral_fee")?,
+            total_fee: attributes.uint128("total_fee")?,
+        }),
+        WithdrawEvent::ACTION => PredictionEvent::Withdraw(WithdrawEvent {
+            market_id: attributes.u64("market_id")?,
+            recipient: attributes.addr("recipient")?,
+            amount: attributes.uint128("amount")?,
+        }),
+        PauseEvent::ACTION => PredictionEvent::Pause(PauseEvent {
+            market_id: attributes.u64("market_id")?,
+            paused: attributes.bool("paused")?,
+        }),
+        EmergencyEvent::ACTION => PredictionEvent::Emergency(EmergencyEvent {
+            market_id: attributes.u64("market_id")?,
+            emergency: attributes.bool("emergency")?,
+        }),
+        UpdateConfigEvent::ACTION => PredictionEvent::UpdateConfig(UpdateConfigEvent {
+            owner_addr: attributes.addr("owner_addr")?,
+            operator_addr: attributes.addr("operator_addr")?,
+            public_execution: attributes.bool("public_execution")?,
+        }),
+        ProposeOwnerEvent::ACTION => PredictionEvent::ProposeOwner(ProposeOwnerEvent {
+            addr: attributes.addr("addr")?,
+            expires_at: attributes.may_u64("expires_at")?,
+        }),
+        AcceptOwnershipEvent::ACTION => PredictionEvent::AcceptOwnership(AcceptOwnershipEvent {
+            owner_addr: attributes.addr("owner_addr")?,
+        }),
+        ProposeOperatorEvent::ACTION => PredictionEvent::ProposeOperator(ProposeOperatorEvent {
+            addr: attributes.addr("addr")?,
+            expires_at: attributes.may_u64("expires_at")?,
+        }),
+        AcceptOperatorEvent::ACTION => PredictionEvent::AcceptOperator(AcceptOperatorEvent {
+            operator_addr: attributes.addr("operator_addr")?,
+        }),
+        RegisterMarketEvent::ACTION => PredictionEvent::RegisterMarket(RegisterMarketEvent {
+            market_id: attributes.u64("market_id")?,
+        }),
+        UpdateMarketEvent::ACTION => PredictionEvent::UpdateMarket(UpdateMarketEvent {
+            market_id: attributes.u64("market_id")?,
+        }),
+        MigrateLegacyEvent::ACTION => PredictionEvent::MigrateLegacy(MigrateLegacyEvent {
+            market_id: attributes.u64("market_id")?,
+            migrated_epoch: attributes.uint128("migrated_epoch")?,
+            last_epoch: attributes.uint128("last_epoch")?,
+            total_fee: attributes.uint128("total_fee")?,
+        }),
+        MigrateBetsEvent::ACTION => PredictionEvent::MigrateBets(MigrateBetsEvent {
+            market_id: attributes.u64("market_id")?,
+            user: attributes.addr("user")?,
+            migrated: attributes.u64("migrated")?,
+        }),
+        _ => return Err(StdError::generic_err(format!("Unknown action: {}", action))),
+    };
+
+    Ok(event)
+}
+
+/// Attributes of an event following `action`
+struct Attributes<'a>(&'a [LogAttribute]);
+
+impl<'a> Attributes<'a> {
+    fn may_get(&self, key: &str) -> Option<&'a str> {
+        self.0
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    fn get(&self, key: &str) -> StdResult<&'a str> {
+        self.may_get(key)
+            .ok_or_else(|| StdError::generic_err(format!("Missing attribute: {}", key)))
+    }
+
+    fn invalid(key: &str) -> StdError {
+        StdError::generic_err(format!("Invalid attribute: {}", key))
+    }
+
+    fn u64(&self, key: &str) -> StdResult<u64> {
+        self.get(key)?.parse().map_err(|_| Self::invalid(key))
+    }
+
+    fn may_u64(&self, key: &str) -> StdResult<Option<u64>> {
+        match self.may_get(key) {
+            Some(value) => Ok(Some(value.parse().map_err(|_| Self::invalid(key))?)),
+            None => Ok(None),
+        }
+    }
+
+    fn bool(&self, key: &str) -> StdResult<bool> {
+        self.get(key)?.parse().map_err(|_| Self::invalid(key))
+    }
+
+    fn uint128(&self, key: &str) -> StdResult<Uint128> {
+        Uint128::try_from(self.get(key)?).map_err(|_| Self::invalid(key))
+    }
+
+    fn addr(&self, key: &str) -> StdResult<HumanAddr> {
+        Ok(HumanAddr::from(self.get(key)?))
+    }
+
+    fn position(&self, key: &str) -> StdResult<Position> {
+        let value = self.get(key)?;
+        match value {
+            "up" => Ok(Position::UP),
+            "down" => Ok(Position::DOWN),
+            "draw" => Ok(Position::DRAW),
+            _ => match value.strip_prefix("bucket_") {
+                Some(index) => Ok(Position::BUCKET(
+                    index.parse().map_err(|_| Self::invalid(key))?,
+                )),
+                None => Err(Self::invalid(key)),
+            },
+        }
+    }
+
+    fn fee_source(&self, key: &str) -> StdResult<FeeSource> {
+        match self.get(key)? {
+            "round" => Ok(FeeSource::Round),
+            "cancel_penalty" => Ok(FeeSource::CancelPenalty),
+            "switch_penalty" => Ok(FeeSource::SwitchPenalty),
+            "reward_dust" => Ok(FeeSource::RewardDust),
+            "referral_dust" => Ok(FeeSource::ReferralDust),
+            "keeper_bounty" => Ok(FeeSource::KeeperBounty),
+            "legacy_refund" => Ok(FeeSource::LegacyRefund),
+            _ => Err(Self::invalid(key)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::log;
+
+    fn addr(addr: &str) -> HumanAddr {
+        HumanAddr::from(addr)
+    }
+
+    fn assert_round_trip<E: Event>(event: E, expected: PredictionEvent) {
+        assert_eq!(parse_events(&event.to_logs()).unwrap(), vec![expected]);
+    }
+
+    #[test]
+    fn parse_user_events() {
+        for (position, referrer) in [
+            (Position::UP, Some(addr("referrer"))),
+            (Position::DRAW, None),
+            (Position::BUCKET(12), None),
+        ]
+        .iter()
+        {
+            let event = BetEvent {
+                market_id: 1,
+                epoch: Uint128(10),
+                user: addr("user"),
+                position: position.clone(),
+                amount: Uint128(100),
+                bet_amount: Uint128(300),
+                referrer: referrer.clone(),
+            };
+            assert_round_trip(event.clone(), PredictionEvent::Bet(event));
+        }
+
+        let event = CancelBetEvent {
+            market_id: 1,
+            epoch: Uint128(10),
+            user: addr("user"),
+            amount: Uint128(95),
+            penalty: Uint128(5),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::CancelBet(event));
+
+        let event = SwitchPositionEvent {
+            market_id: 2,
+            epoch: Uint128(10),
+            user: addr("user"),
+            position: Position::BUCKET(0),
+            amount: Uint128(95),
+            penalty: Uint128(5),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::SwitchPosition(event));
+
+        let event = ClaimEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            user: addr("user"),
+            recipient: addr("recipient"),
+            amount: Uint128(190),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::Claim(event));
+
+        let event = ClaimerEvent {
+            user: addr("user"),
+            claimer: addr("claimer"),
+            allowed: false,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::Claimer(event));
+
+        let event = ClaimReferralRewardsEvent {
+            market_id: 1,
+            referrer: addr("referrer"),
+            amount: Uint128(0),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::ClaimReferralRewards(event));
+    }
+
+    #[test]
+    fn parse_round_events() {
+        let event = RoundStartEvent {
+            market_id: 1,
+            epoch: Uint128(10),
+            start_time: 1000,
+            lock_time: 1600,
+            end_time: 2200,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::RoundStart(event));
+
+        let event = RoundLockEvent {
+            market_id: 1,
+            epoch: Uint128(10),
+            open_price: Uint128(1_000_000),
+            total_amount: Uint128(0),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::RoundLock(event));
+
+        let event = RoundCloseEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            close_price: Uint128(1_000_001),
+            total_amount: Uint128(1000),
+            reward_amount: Uint128(970),
+            executor: addr("keeper"),
+            bounty: Uint128(10),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::RoundClose(event));
+
+        let event = RoundCancelEvent {
+            market_id: 1,
+            epoch: Uint128(8),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::RoundCancel(event));
+
+        let event = DistributeRoundEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            distributed_count: 30,
+            bettor_count: 45,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::DistributeRound(event));
+
+        let event = FeeAccrualEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            source: FeeSource::Round,
+            fee: Uint128(30),
+            referral_fee: Uint128(3),
+            total_fee: Uint128(127),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::FeeAccrual(event));
+
+        for source in [
+            FeeSource::CancelPenalty,
+            FeeSource::SwitchPenalty,
+            FeeSource::RewardDust,
+            FeeSource::ReferralDust,
+            FeeSource::KeeperBounty,
+            FeeSource::LegacyRefund,
+        ]
+        .iter()
+        {
+            let event = FeeAccrualEvent {
+                market_id: 1,
+                epoch: Uint128(9),
+                source: source.clone(),
+                fee: Uint128(2),
+                referral_fee: Uint128(0),
+                total_fee: Uint128(100),
+            };
+            assert_round_trip(event.clone(), PredictionEvent::FeeAccrual(event));
+        }
+    }
+
+    #[test]
+    fn parse_admin_events() {
+        let event = WithdrawEvent {
+            market_id: 1,
+            recipient: addr("treasury"),
+            amount: Uint128(27),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::Withdraw(event));
+
+        let event = PauseEvent {
+            market_id: 1,
+            paused: true,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::Pause(event));
+
+        let event = EmergencyEvent {
+            market_id: 1,
+            emergency: false,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::Emergency(event));
+
+        let event = UpdateConfigEvent {
+            owner_addr: addr("owner"),
+            operator_addr: addr("operator"),
+            public_execution: true,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::UpdateConfig(event));
+
+        for expires_at in [Some(86400), None].iter() {
+            let event = ProposeOwnerEvent {
+                addr: addr("new_owner"),
+                expires_at: *expires_at,
+            };
+            assert_round_trip(event.clone(), PredictionEvent::ProposeOwner(event));
+
+            let event = ProposeOperatorEvent {
+                addr: addr("new_operator"),
+                expires_at: *expires_at,
+            };
+            assert_round_trip(event.clone(), PredictionEvent::ProposeOperator(event));
+        }
+
+        let event = AcceptOwnershipEvent {
+            owner_addr: addr("new_owner"),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::AcceptOwnership(event));
+
+        let event = AcceptOperatorEvent {
+            operator_addr: addr("new_operator"),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::AcceptOperator(event));
+
+        let event = RegisterMarketEvent { market_id: 3 };
+        assert_round_trip(event.clone(), PredictionEvent::RegisterMarket(event));
+
+        let event = UpdateMarketEvent { market_id: 3 };
+        assert_round_trip(event.clone(), PredictionEvent::UpdateMarket(event));
+
+        let event = MigrateLegacyEvent {
+            market_id: 1,
+            migrated_epoch: Uint128(30),
+            last_epoch: Uint128(42),
+            total_fee: Uint128(1200),
+        };
+        assert_round_trip(event.clone(), PredictionEvent::MigrateLegacy(event));
+
+        let event = MigrateBetsEvent {
+            market_id: 1,
+            user: addr("alice"),
+            migrated: 4,
+        };
+        assert_round_trip(event.clone(), PredictionEvent::MigrateBets(event));
+    }
+
+    #[test]
+    fn parse_execute_round_with_auto_payout() {
+        let close = RoundCloseEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            close_price: Uint128(99),
+            total_amount: Uint128(1000),
+            reward_amount: Uint128(970),
+            executor: addr("operator"),
+            bounty: Uint128(0),
+        };
+        let fee = FeeAccrualEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            source: FeeSource::Round,
+            fee: Uint128(30),
+            referral_fee: Uint128(0),
+            total_fee: Uint128(30),
+        };
+        let claim = ClaimEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            user: addr("user"),
+            recipient: addr("user"),
+            amount: Uint128(970),
+        };
+        let distribute = DistributeRoundEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            distributed_count: 2,
+            bettor_count: 2,
+        };
+        let lock = RoundLockEvent {
+            market_id: 1,
+            epoch: Uint128(10),
+            open_price: Uint128(99),
+            total_amount: Uint128(500),
+        };
+        let start = RoundStartEvent {
+            market_id: 1,
+            epoch: Uint128(11),
+            start_time: 2200,
+            lock_time: 2800,
+            end_time: 3400,
+        };
+
+        let mut logs = close.to_logs();
+        logs.extend(fee.to_logs());
+        logs.extend(claim.to_logs());
+        logs.extend(distribute.to_logs());
+        logs.extend(lock.to_logs());
+        logs.extend(start.to_logs());
+
+        assert_eq!(
+            parse_events(&logs).unwrap(),
+            vec![
+                PredictionEvent::RoundClose(close),
+                PredictionEvent::FeeAccrual(fee),
+                PredictionEvent::Claim(claim),
+                PredictionEvent::DistributeRound(distribute),
+                PredictionEvent::RoundLock(lock),
+                PredictionEvent::RoundStart(start),
+            ]
+        );
+        assert_eq!(parse_events(&[]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn reject_malformed_logs() {
+        // attributes before action
+        assert!(parse_events(&[log("market_id", 1), log("action", "pause")]).is_err());
+        assert!(parse_event(&[log("market_id", 1)]).is_err());
+        assert!(parse_event(&[]).is_err());
+
+        // unknown action
+        assert!(parse_events(&[log("action", "unknown")]).is_err());
+
+        // missing attribute
+        assert!(parse_events(&[log("action", "pause"), log("market_id", 1)]).is_err());
+
+        // invalid values
+        let invalid_values = [
+            vec![log("market_id", "one"), log("paused", true)],
+            vec![log("market_id", 1), log("paused", "yes")],
+            vec![log("market_id", -1), log("paused", true)],
+        ];
+        for attributes in invalid_values.iter() {
+            let mut logs = vec![log("action", "pause")];
+            logs.extend(attributes.iter().cloned());
+            assert!(parse_events(&logs).is_err());
+        }
+
+        let mut logs = BetEvent {
+            market_id: 1,
+            epoch: Uint128(10),
+            user: addr("user"),
+            position: Position::UP,
+            amount: Uint128(100),
+            bet_amount: Uint128(100),
+            referrer: None,
+        }
+        .to_logs();
+        for (position, valid) in [
+            ("sideways", false),
+            ("bucket_", false),
+            ("bucket_256", false),
+            ("bucket_x", false),
+            ("bucket_255", true),
+        ]
+        .iter()
+        {
+            logs[4] = log("position", position);
+            assert_eq!(parse_events(&logs).is_ok(), *valid);
+        }
+
+        let mut logs = ProposeOwnerEvent {
+            addr: addr("new_owner"),
+            expires_at: Some(1),
+        }
+        .to_logs();
+        logs[2] = log("expires_at", "soon");
+        assert!(parse_events(&logs).is_err());
+
+        let mut logs = ClaimEvent {
+            market_id: 1,
+            epoch: Uint128(9),
+            user: addr("user"),
+            recipient: addr("user"),
+            amount: Uint128(970),
+        }
+        .to_logs();
+        logs[5] = log("amount", "1.5");
+        assert!(parse_events(&logs).is_err());
+    }
+}
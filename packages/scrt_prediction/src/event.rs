@@ -0,0 +1,543 @@
+use cosmwasm_std::{log, HumanAddr, LogAttribute, Uint128};
+
+use crate::prediction::Position;
+
+/// Event of prediction contract, logged as `action` followed by attributes of the event.
+/// Response can contain several events, each of them starts with `action`.
+pub trait Event {
+    const ACTION: &'static str;
+
+    fn attributes(&self) -> Vec<LogAttribute>;
+
+    fn to_logs(&self) -> Vec<LogAttribute> {
+        let mut logs = vec![log("action", Self::ACTION)];
+        logs.extend(self.attributes());
+        logs
+    }
+}
+
+/// Log value of position, `bucket_<index>` for bucket positions
+pub fn position_to_string(position: &Position) -> String {
+    match position {
+        Position::UP => "up".to_string(),
+        Position::DOWN => "down".to_string(),
+        Position::DRAW => "draw".to_string(),
+        Position::BUCKET(index) => format!("bucket_{}", index),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BetEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub user: HumanAddr,
+    pub position: Position,
+    pub amount: Uint128,
+    /// Total bet amount of user in the round
+    pub bet_amount: Uint128,
+    pub referrer: Option<HumanAddr>,
+}
+
+impl Event for BetEvent {
+    const ACTION: &'static str = "bet";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        let mut logs = vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("user", &self.user),
+            log("position", position_to_string(&self.position)),
+            log("amount", self.amount),
+            log("bet_amount", self.bet_amount),
+        ];
+        if let Some(referrer) = &self.referrer {
+            logs.push(log("referrer", referrer));
+        }
+        logs
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CancelBetEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub user: HumanAddr,
+    /// Refunded amount
+    pub amount: Uint128,
+    pub penalty: Uint128,
+}
+
+impl Event for CancelBetEvent {
+    const ACTION: &'static str = "cancel_bet";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("user", &self.user),
+            log("amount", self.amount),
+            log("penalty", self.penalty),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwitchPositionEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub user: HumanAddr,
+    /// New position of bet
+    pub position: Position,
+    /// Bet amount after penalty
+    pub amount: Uint128,
+    pub penalty: Uint128,
+}
+
+impl Event for SwitchPositionEvent {
+    const ACTION: &'static str = "switch_position";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("user", &self.user),
+            log("position", position_to_string(&self.position)),
+            log("amount", self.amount),
+            log("penalty", self.penalty),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub user: HumanAddr,
+    /// Address which received the claimed amount
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+}
+
+impl Event for ClaimEvent {
+    const ACTION: &'static str = "claim";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("user", &self.user),
+            log("recipient", &self.recipient),
+            log("amount", self.amount),
+        ]
+    }
+}
+
+/// Change of addresses allowed to claim on behalf of user
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimerEvent {
+    pub user: HumanAddr,
+    pub claimer: HumanAddr,
+    pub allowed: bool,
+}
+
+impl Event for ClaimerEvent {
+    const ACTION: &'static str = "update_claimer";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("user", &self.user),
+            log("claimer", &self.claimer),
+            log("allowed", self.allowed),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimReferralRewardsEvent {
+    pub market_id: u64,
+    pub referrer: HumanAddr,
+    pub amount: Uint128,
+}
+
+impl Event for ClaimReferralRewardsEvent {
+    const ACTION: &'static str = "claim_referral_rewards";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("referrer", &self.referrer),
+            log("amount", self.amount),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundStartEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub start_time: u64,
+    pub lock_time: u64,
+    pub end_time: u64,
+}
+
+impl Event for RoundStartEvent {
+    const ACTION: &'static str = "round_start";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("start_time", self.start_time),
+            log("lock_time", self.lock_time),
+            log("end_time", self.end_time),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundLockEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub open_price: Uint128,
+    pub total_amount: Uint128,
+}
+
+impl Event for RoundLockEvent {
+    const ACTION: &'static str = "round_lock";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("open_price", self.open_price),
+            log("total_amount", self.total_amount),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundCloseEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub close_price: Uint128,
+    pub total_amount: Uint128,
+    pub reward_amount: Uint128,
+    pub executor: HumanAddr,
+    pub bounty: Uint128,
+}
+
+impl Event for RoundCloseEvent {
+    const ACTION: &'static str = "round_close";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("close_price", self.close_price),
+            log("total_amount", self.total_amount),
+            log("reward_amount", self.reward_amount),
+            log("executor", &self.executor),
+            log("bounty", self.bounty),
+        ]
+    }
+}
+
+/// Progress of auto payout of round
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributeRoundEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    /// Number of bettors processed so far
+    pub distributed_count: u64,
+    pub bettor_count: u64,
+}
+
+impl Event for DistributeRoundEvent {
+    const ACTION: &'static str = "distribute_round";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("distributed_count", self.distributed_count),
+            log("bettor_count", self.bettor_count),
+        ]
+    }
+}
+
+/// Round which is not closed in time, its bettors are refundable
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundCancelEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+}
+
+impl Event for RoundCancelEvent {
+    const ACTION: &'static str = "round_cancel";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![log("market_id", self.market_id), log("epoch", self.epoch)]
+    }
+}
+
+/// Cause of change of accrued fee
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeeSource {
+    /// Fee taken from closed round
+    Round,
+    /// Penalty of cancelled bet
+    CancelPenalty,
+    /// Penalty of switched bet
+    SwitchPenalty,
+    /// Rounding dust of rewards once all bets of round are paid
+    RewardDust,
+    /// Rounding dust of referral rewards once all referrers of round are paid
+    ReferralDust,
+    /// Bounty paid to keeper from fee, deducted
+    KeeperBounty,
+    /// Fee of one-sided legacy round which is refunded now, deducted
+    LegacyRefund,
+}
+
+/// Log value of fee source
+pub fn fee_source_to_string(source: &FeeSource) -> String {
+    match source {
+        FeeSource::Round => "round",
+        FeeSource::CancelPenalty => "cancel_penalty",
+        FeeSource::SwitchPenalty => "switch_penalty",
+        FeeSource::RewardDust => "reward_dust",
+        FeeSource::ReferralDust => "referral_dust",
+        FeeSource::KeeperBounty => "keeper_bounty",
+        FeeSource::LegacyRefund => "legacy_refund",
+    }
+    .to_string()
+}
+
+/// Change of fee accrued by market, logged whenever accrued fee changes except withdrawal
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeAccrualEvent {
+    pub market_id: u64,
+    pub epoch: Uint128,
+    pub source: FeeSource,
+    /// Fee added, or deducted for deduction sources. Fee of round includes referral fee
+    pub fee: Uint128,
+    /// Share of fee reserved for referrers
+    pub referral_fee: Uint128,
+    /// Accrued fee of market after the change
+    pub total_fee: Uint128,
+}
+
+impl Event for FeeAccrualEvent {
+    const ACTION: &'static str = "fee_accrual";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("epoch", self.epoch),
+            log("source", fee_source_to_string(&self.source)),
+            log("fee", self.fee),
+            log("referral_fee", self.referral_fee),
+            log("total_fee", self.total_fee),
+        ]
+    }
+}
+
+/// Fee sent to a fee recipient
+#[derive(Clone, Debug, PartialEq)]
+pub struct WithdrawEvent {
+    pub market_id: u64,
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+}
+
+impl Event for WithdrawEvent {
+    const ACTION: &'static str = "withdraw";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("recipient", &self.recipient),
+            log("amount", self.amount),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PauseEvent {
+    pub market_id: u64,
+    pub paused: bool,
+}
+
+impl Event for PauseEvent {
+    const ACTION: &'static str = "pause";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![log("market_id", self.market_id), log("paused", self.paused)]
+    }
+}
+
+/// Emergency mode of market, in which unsettled rounds are cancelled and refundable
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmergencyEvent {
+    pub market_id: u64,
+    pub emergency: bool,
+}
+
+impl Event for EmergencyEvent {
+    const ACTION: &'static str = "emergency";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("emergency", self.emergency),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateConfigEvent {
+    pub owner_addr: HumanAddr,
+    pub operator_addr: HumanAddr,
+    pub public_execution: bool,
+}
+
+impl Event for UpdateConfigEvent {
+    const ACTION: &'static str = "update_config";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("owner_addr", &self.owner_addr),
+            log("operator_addr", &self.operator_addr),
+            log("public_execution", self.public_execution),
+        ]
+    }
+}
+
+/// Proposal of ownership transfer
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposeOwnerEvent {
+    pub addr: HumanAddr,
+    pub expires_at: Option<u64>,
+}
+
+impl Event for ProposeOwnerEvent {
+    const ACTION: &'static str = "propose_owner";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        propose_attributes(&self.addr, self.expires_at)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcceptOwnershipEvent {
+    pub owner_addr: HumanAddr,
+}
+
+impl Event for AcceptOwnershipEvent {
+    const ACTION: &'static str = "accept_ownership";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![log("owner_addr", &self.owner_addr)]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposeOperatorEvent {
+    pub addr: HumanAddr,
+    pub expires_at: Option<u64>,
+}
+
+impl Event for ProposeOperatorEvent {
+    const ACTION: &'static str = "propose_operator";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        propose_attributes(&self.addr, self.expires_at)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcceptOperatorEvent {
+    pub operator_addr: HumanAddr,
+}
+
+impl Event for AcceptOperatorEvent {
+    const ACTION: &'static str = "accept_operator";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![log("operator_addr", &self.operator_addr)]
+    }
+}
+
+fn propose_attributes(addr: &HumanAddr, expires_at: Option<u64>) -> Vec<LogAttribute> {
+    let mut logs = vec![log("addr", addr)];
+    if let Some(expires_at) = expires_at {
+        logs.push(log("expires_at", expires_at));
+    }
+    logs
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterMarketEvent {
+    pub market_id: u64,
+}
+
+impl Event for RegisterMarketEvent {
+    const ACTION: &'static str = "register_market";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![log("market_id", self.market_id)]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateMarketEvent {
+    pub market_id: u64,
+}
+
+impl Event for UpdateMarketEvent {
+    const ACTION: &'static str = "update_market";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![log("market_id", self.market_id)]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrateLegacyEvent {
+    pub market_id: u64,
+    pub migrated_epoch: Uint128,
+    pub last_epoch: Uint128,
+    pub total_fee: Uint128,
+}
+
+impl Event for MigrateLegacyEvent {
+    const ACTION: &'static str = "migrate_legacy";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("migrated_epoch", self.migrated_epoch),
+            log("last_epoch", self.last_epoch),
+            log("total_fee", self.total_fee),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrateBetsEvent {
+    pub market_id: u64,
+    pub user: HumanAddr,
+    pub migrated: u64,
+}
+
+impl Event for MigrateBetsEvent {
+    const ACTION: &'static str = "migrate_bets";
+
+    fn attributes(&self) -> Vec<LogAttribute> {
+        vec![
+            log("market_id", self.market_id),
+            log("user", &self.user),
+            log("migrated", self.migrated),
+        ]
+    }
+}
@@ -0,0 +1,107 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::asset::AssetInfo;
+use crate::prediction::{MarketParams, PendingTransferResponse};
+use cosmwasm_std::{Decimal, HumanAddr};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitMsg {
+    /// Operator address of created markets
+    pub operator_addr: HumanAddr,
+    /// Treasury address of created markets
+    pub treasury_addr: HumanAddr,
+    /// Oracle contract code id
+    pub oracle_code_id: u64,
+    /// Oracle contract code hash
+    pub oracle_code_hash: String,
+    /// Prediction contract code id
+    pub prediction_code_id: u64,
+    /// Prediction contract code hash
+    pub prediction_code_hash: String,
+    /// Band oracle address used by created oracles
+    pub band_oracle: HumanAddr,
+    /// Band oracle code hash
+    pub band_oracle_code_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    UpdateConfig {
+        operator_addr: Option<HumanAddr>,
+        treasury_addr: Option<HumanAddr>,
+        oracle_code_id: Option<u64>,
+        oracle_code_hash: Option<String>,
+        prediction_code_id: Option<u64>,
+        prediction_code_hash: Option<String>,
+        band_oracle: Option<HumanAddr>,
+        band_oracle_code_hash: Option<String>,
+    },
+    /// Propose new owner, who becomes owner once accepted.
+    /// Proposal expires after `expires_in` seconds if given
+    ProposeOwner {
+        addr: HumanAddr,
+        expires_in: Option<u64>,
+    },
+    /// Accept ownership proposed to sender
+    AcceptOwnership {},
+    /// Instantiate oracle and prediction contracts for new market
+    CreateMarket {
+        base_symbol: String,
+        quote_symbol: String,
+        bet_asset: AssetInfo,
+        public_execution: bool,
+        referral_fee_rate: Decimal,
+        params: MarketParams,
+    },
+    /// Init callback of oracle contract
+    RegisterOracle {},
+    /// Init callback of prediction contract
+    RegisterPrediction {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// Query current configuration
+    Config {},
+    /// Query created market
+    Market { market_id: u64 },
+    /// Query created markets
+    Markets {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner_addr: HumanAddr,
+    pub operator_addr: HumanAddr,
+    pub treasury_addr: HumanAddr,
+    pub oracle_code_id: u64,
+    pub oracle_code_hash: String,
+    pub prediction_code_id: u64,
+    pub prediction_code_hash: String,
+    pub band_oracle: HumanAddr,
+    pub band_oracle_code_hash: String,
+    pub market_count: u64,
+    pub pending_owner: Option<PendingTransferResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketResponse {
+    pub market_id: u64,
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub bet_asset: AssetInfo,
+    pub oracle_addr: HumanAddr,
+    pub prediction_addr: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketsResponse {
+    pub markets: Vec<MarketResponse>,
+}
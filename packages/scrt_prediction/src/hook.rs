@@ -0,0 +1,23 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Binary, CosmosMsg, HumanAddr, StdResult, WasmMsg};
+
+/// Message executed on `contract_addr` once the contract is instantiated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitHook {
+    pub msg: Binary,
+    pub contract_addr: HumanAddr,
+    pub code_hash: String,
+}
+
+impl InitHook {
+    pub fn into_msg(self) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: self.contract_addr,
+            callback_code_hash: self.code_hash,
+            msg: self.msg,
+            send: vec![],
+        }))
+    }
+}
@@ -1,3 +1,7 @@
 pub mod asset;
 pub mod prediction;
-pub mod oracle;
\ No newline at end of file
+pub mod oracle;
+pub mod factory;
+pub mod hook;
+pub mod event;
+pub mod ownership;
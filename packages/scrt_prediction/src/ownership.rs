@@ -0,0 +1,65 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::prediction::PendingTransferResponse;
+use cosmwasm_std::{Api, CanonicalAddr, Env, Extern, Querier, StdError, StdResult, Storage};
+
+/// Address proposed to take over a role, which has not accepted yet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTransfer {
+    pub addr: CanonicalAddr,
+    /// Transfer cannot be accepted from this time
+    pub expires_at: Option<u64>,
+}
+
+impl PendingTransfer {
+    /// Proposal which expires after `expires_in` seconds if given
+    pub fn new(env: &Env, addr: CanonicalAddr, expires_in: Option<u64>) -> StdResult<Self> {
+        let expires_at = match expires_in {
+            Some(0) => return Err(StdError::generic_err("Invalid expiry")),
+            Some(expires_in) => Some(
+                env.block
+                    .time
+                    .checked_add(expires_in)
+                    .ok_or_else(|| StdError::generic_err("Invalid expiry"))?,
+            ),
+            None => None,
+        };
+
+        Ok(PendingTransfer { addr, expires_at })
+    }
+
+    pub fn is_expired(&self, time: u64) -> bool {
+        match self.expires_at {
+            Some(expires_at) => time >= expires_at,
+            None => false,
+        }
+    }
+
+    pub fn to_normal<S: Storage, A: Api, Q: Querier>(
+        &self,
+        deps: &Extern<S, A, Q>,
+    ) -> StdResult<PendingTransferResponse> {
+        Ok(PendingTransferResponse {
+            addr: deps.api.human_address(&self.addr)?,
+            expires_at: self.expires_at,
+        })
+    }
+}
+
+/// Returns new address if sender is the pending address and transfer is not expired
+pub fn accept_transfer(
+    pending: Option<PendingTransfer>,
+    env: &Env,
+    sender_raw: CanonicalAddr,
+) -> StdResult<CanonicalAddr> {
+    let pending = match pending {
+        Some(pending) if pending.addr == sender_raw => pending,
+        _ => return Err(StdError::unauthorized()),
+    };
+    if pending.is_expired(env.block.time) {
+        return Err(StdError::generic_err("Transfer expired"));
+    }
+
+    Ok(pending.addr)
+}
@@ -2,14 +2,34 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::asset::AssetInfo;
+use crate::hook::InitHook;
 use cosmwasm_std::{Binary, Decimal, HumanAddr, Uint128};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     /// Operator address
     pub operator_addr: HumanAddr,
-    /// Treasury address
-    pub treasury_addr: HumanAddr,
+    /// Weighted recipients of protocol fee, weights should sum to 1
+    pub fee_recipients: Vec<FeeRecipientInfo>,
+    /// Allow anyone to execute round after keeper delay
+    pub public_execution: bool,
+    /// Share of fee from referred users' bets accrued to referrers
+    pub referral_fee_rate: Decimal,
+    /// Markets to register
+    pub markets: Vec<MarketInfo>,
+    /// Message to execute after instantiation
+    pub init_hook: Option<InitHook>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRecipientInfo {
+    pub addr: HumanAddr,
+    /// Share of protocol fee
+    pub weight: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketInfo {
     /// Asset to bet
     pub bet_asset: AssetInfo,
     /// Price oracle address
@@ -18,10 +38,89 @@ pub struct InitMsg {
     pub oracle_code_hash: String,
     /// Fee rate
     pub fee_rate: Decimal,
+    /// Whether fee is taken from whole pool or from losing pool
+    pub fee_mode: FeeMode,
     /// Interval of each round in seconds
     pub interval: u64,
     /// Grace interval to execute round
     pub grace_interval: u64,
+    /// Delay after round end before anyone can execute round
+    pub keeper_delay: u64,
+    /// Bounty paid from fee to whom executes round other than operator
+    pub keeper_bounty: Uint128,
+    /// Minimum bet amount
+    pub min_bet: Uint128,
+    /// Maximum bet amount of each user in a round, zero for no limit
+    pub max_bet_per_user: Uint128,
+    /// Maximum total bet amount of a round, zero for no limit
+    pub max_round_pool: Uint128,
+    /// Penalty fee rate to cancel bet or switch position
+    pub cancel_fee_rate: Decimal,
+    /// Ascending price change bounds of range buckets in basis points, empty for UP/DOWN market
+    pub bucket_bounds: Vec<i64>,
+    /// Settlement of UP/DOWN rounds closed with unchanged price
+    pub draw_policy: DrawPolicy,
+    /// Send rewards and refunds of closed rounds to bettors without claim
+    pub auto_payout: bool,
+}
+
+/// Parameters of market other than its bet asset and oracle, given to the factory
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketParams {
+    /// Fee rate
+    pub fee_rate: Decimal,
+    /// Whether fee is taken from whole pool or from losing pool
+    pub fee_mode: FeeMode,
+    /// Interval of each round in seconds
+    pub interval: u64,
+    /// Grace interval to execute round
+    pub grace_interval: u64,
+    /// Delay after round end before anyone can execute round
+    pub keeper_delay: u64,
+    /// Bounty paid from fee to whom executes round other than operator
+    pub keeper_bounty: Uint128,
+    /// Minimum bet amount
+    pub min_bet: Uint128,
+    /// Maximum bet amount of each user in a round, zero for no limit
+    pub max_bet_per_user: Uint128,
+    /// Maximum total bet amount of a round, zero for no limit
+    pub max_round_pool: Uint128,
+    /// Penalty fee rate to cancel bet or switch position
+    pub cancel_fee_rate: Decimal,
+    /// Ascending price change bounds of range buckets in basis points, empty for UP/DOWN market
+    pub bucket_bounds: Vec<i64>,
+    /// Settlement of UP/DOWN rounds closed with unchanged price
+    pub draw_policy: DrawPolicy,
+    /// Send rewards and refunds of closed rounds to bettors without claim
+    pub auto_payout: bool,
+}
+
+impl MarketParams {
+    pub fn into_market_info(
+        self,
+        bet_asset: AssetInfo,
+        oracle_addr: HumanAddr,
+        oracle_code_hash: String,
+    ) -> MarketInfo {
+        MarketInfo {
+            bet_asset,
+            oracle_addr,
+            oracle_code_hash,
+            fee_rate: self.fee_rate,
+            fee_mode: self.fee_mode,
+            interval: self.interval,
+            grace_interval: self.grace_interval,
+            keeper_delay: self.keeper_delay,
+            keeper_bounty: self.keeper_bounty,
+            min_bet: self.min_bet,
+            max_bet_per_user: self.max_bet_per_user,
+            max_round_pool: self.max_round_pool,
+            cancel_fee_rate: self.cancel_fee_rate,
+            bucket_bounds: self.bucket_bounds,
+            draw_policy: self.draw_policy,
+            auto_payout: self.auto_payout,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -34,33 +133,121 @@ pub enum HandleMsg {
     },
     /// Update configuration
     UpdateConfig {
-        owner_addr: Option<HumanAddr>,
-        operator_addr: Option<HumanAddr>,
-        treasury_addr: Option<HumanAddr>,
+        fee_recipients: Option<Vec<FeeRecipientInfo>>,
+        public_execution: Option<bool>,
+        referral_fee_rate: Option<Decimal>,
+    },
+    /// Propose new owner, who becomes owner once accepted.
+    /// Proposal expires after `expires_in` seconds if given
+    ProposeOwner {
+        addr: HumanAddr,
+        expires_in: Option<u64>,
+    },
+    /// Accept ownership proposed to sender
+    AcceptOwnership {},
+    /// Propose new operator, who becomes operator once accepted.
+    /// Proposal expires after `expires_in` seconds if given
+    ProposeOperator {
+        addr: HumanAddr,
+        expires_in: Option<u64>,
+    },
+    /// Accept operator role proposed to sender
+    AcceptOperator {},
+    /// Register new market
+    RegisterMarket { market: MarketInfo },
+    /// Update market configuration
+    UpdateMarket {
+        market_id: u64,
         oracle_addr: Option<HumanAddr>,
         oracle_code_hash: Option<String>,
         fee_rate: Option<Decimal>,
+        fee_mode: Option<FeeMode>,
         interval: Option<u64>,
         grace_interval: Option<u64>,
+        keeper_delay: Option<u64>,
+        keeper_bounty: Option<Uint128>,
+        min_bet: Option<Uint128>,
+        max_bet_per_user: Option<Uint128>,
+        max_round_pool: Option<Uint128>,
+        cancel_fee_rate: Option<Decimal>,
+        draw_policy: Option<DrawPolicy>,
+        auto_payout: Option<bool>,
+    },
+    /// Bet, referrer is stored on first use
+    Bet {
+        market_id: u64,
+        position: Position,
+        referrer: Option<HumanAddr>,
     },
-    /// Bet
-    Bet { position: Position },
-    /// Claim winner reward
-    Claim { epoch: Uint128 },
-    /// Finish ongoing round, lock betting round and start new round
-    ExecuteRound {},
-    /// Withdraw performance fee to treasury
-    Withdraw {},
+    /// Cancel bet of betting round with penalty
+    CancelBet { market_id: u64 },
+    /// Switch bet in betting round to another position with penalty
+    SwitchPosition { market_id: u64, position: Position },
+    /// Claim winner reward, sent to recipient if given
+    Claim {
+        market_id: u64,
+        epoch: Uint128,
+        recipient: Option<HumanAddr>,
+    },
+    /// Claim winner reward on behalf of user who allowed the sender, sent to the user
+    ClaimFor {
+        market_id: u64,
+        user: HumanAddr,
+        epoch: Uint128,
+    },
+    /// Allow address to claim on behalf of the sender
+    AddClaimer { claimer: HumanAddr },
+    /// Revoke address allowed to claim on behalf of the sender
+    RemoveClaimer { claimer: HumanAddr },
+    /// Claim winner rewards and refunds of multiple rounds, up to 30 epochs
+    ClaimMany {
+        market_id: u64,
+        epochs: Vec<Uint128>,
+    },
+    /// Claim all unclaimed winner rewards and refunds, up to limit rounds
+    ClaimAll { market_id: u64, limit: Option<u32> },
+    /// Claim referral rewards of settled rounds, up to limit rounds
+    ClaimReferralRewards { market_id: u64, limit: Option<u32> },
+    /// Finish ongoing round, lock betting round and start new round.
+    /// Anyone can execute after keeper delay if public execution is enabled
+    ExecuteRound { market_id: u64 },
+    /// Send rewards and refunds of closed round to its bettors, up to limit bettors.
+    /// Continues from the last distributed bettor, only for market with auto payout
+    DistributeRound {
+        market_id: u64,
+        epoch: Uint128,
+        limit: Option<u32>,
+    },
+    /// Withdraw performance fee to fee recipients
+    Withdraw { market_id: u64 },
     /// Pause
-    Pause {},
+    Pause { market_id: u64 },
+    /// Pause market and cancel unsettled rounds, so all unsettled bets are refundable.
+    /// Market cannot be started again until emergency mode is disabled
+    EnableEmergency { market_id: u64 },
+    /// Disable emergency mode, only after 7 days from when it was enabled
+    DisableEmergency { market_id: u64 },
     /// Start genesis round
-    StartGenesisRound {},
+    StartGenesisRound { market_id: u64 },
+    /// Cancel expired rounds and start genesis round again
+    RecoverRounds { market_id: u64 },
+    /// Move config, state and up to limit rounds of the single market contract into market 1
+    MigrateLegacy { limit: Option<u32> },
+    /// Move bets of user stored by the single market contract into market 1
+    MigrateBets {
+        user: HumanAddr,
+        epochs: Vec<Uint128>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
-    Bet { position: Position },
+    Bet {
+        market_id: u64,
+        position: Position,
+        referrer: Option<HumanAddr>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -68,12 +255,57 @@ pub enum Cw20HookMsg {
 pub enum QueryMsg {
     /// Query current configuration
     Config {},
-    /// Query current state
-    State {},
+    /// Query market configuration
+    Market { market_id: u64 },
+    /// Query market configurations
+    Markets {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Query current state of market
+    State { market_id: u64 },
     /// Query round by epoch
-    Round { epoch: Uint128 },
+    Round { market_id: u64, epoch: Uint128 },
     /// Query bet by user and epoch
-    Bet { epoch: Uint128, user: HumanAddr },
+    Bet {
+        market_id: u64,
+        epoch: Uint128,
+        user: HumanAddr,
+    },
+    /// Query rounds with status
+    Rounds {
+        market_id: u64,
+        start_after: Option<Uint128>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    /// Query bets of user with round results
+    UserBets {
+        market_id: u64,
+        user: HumanAddr,
+        start_after: Option<Uint128>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    /// Query stacked fee of each fee recipient
+    FeeRecipients { market_id: u64 },
+    /// Query performance of user
+    UserStats { market_id: u64, user: HumanAddr },
+    /// Query top users by metric
+    Leaderboard {
+        market_id: u64,
+        metric: LeaderboardMetric,
+        limit: Option<u32>,
+    },
+    /// Query referral stats of referrer
+    ReferralStats { market_id: u64, referrer: HumanAddr },
+    /// Query addresses allowed to claim on behalf of user
+    Claimers { user: HumanAddr },
+    /// Query liabilities and balance of each bet asset, up to limit markets
+    Solvency {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 // We define a custom struct for each query response
@@ -81,13 +313,137 @@ pub enum QueryMsg {
 pub struct ConfigResponse {
     pub owner_addr: HumanAddr,
     pub operator_addr: HumanAddr,
-    pub treasury_addr: HumanAddr,
+    pub fee_recipients: Vec<FeeRecipientInfo>,
+    pub public_execution: bool,
+    pub referral_fee_rate: Decimal,
+    pub market_count: u64,
+    pub pending_owner: Option<PendingTransferResponse>,
+    pub pending_operator: Option<PendingTransferResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTransferResponse {
+    pub addr: HumanAddr,
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketResponse {
+    pub market_id: u64,
     pub bet_asset: AssetInfo,
     pub oracle_addr: HumanAddr,
     pub oracle_code_hash: String,
     pub fee_rate: Decimal,
+    pub fee_mode: FeeMode,
     pub interval: u64,
     pub grace_interval: u64,
+    pub keeper_delay: u64,
+    pub keeper_bounty: Uint128,
+    pub min_bet: Uint128,
+    pub max_bet_per_user: Uint128,
+    pub max_round_pool: Uint128,
+    pub cancel_fee_rate: Decimal,
+    pub bucket_bounds: Vec<i64>,
+    pub draw_policy: DrawPolicy,
+    pub auto_payout: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketsResponse {
+    pub markets: Vec<MarketResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRecipientResponse {
+    pub addr: HumanAddr,
+    pub weight: Decimal,
+    /// Stacked fee which is not withdrawn yet
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRecipientsResponse {
+    pub recipients: Vec<FeeRecipientResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStatsResponse {
+    pub user: HumanAddr,
+    /// Number of settled rounds which are won or lost
+    pub rounds_played: u64,
+    pub total_wagered: Uint128,
+    pub total_won: Uint128,
+    /// Total won amount minus total wagered amount, negative for net loss
+    pub net_pnl: String,
+    pub current_streak: u64,
+    pub best_streak: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    RoundsPlayed,
+    TotalWagered,
+    TotalWon,
+    NetProfit,
+    BestStreak,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntry {
+    pub user: HumanAddr,
+    pub value: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardResponse {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetSolvencyResponse {
+    pub asset: AssetInfo,
+    /// Bets of rounds which are not settled
+    pub open_pool: Uint128,
+    /// Rewards and refunds of settled rounds which are not paid
+    pub unclaimed_amount: Uint128,
+    /// Referral fee reserved for referrers which is not claimed
+    pub referral_rewards: Uint128,
+    /// Fee which is not withdrawn
+    pub total_fee: Uint128,
+    /// Sum of all liabilities above
+    pub liabilities: Uint128,
+    /// Balance of the contract
+    pub balance: Uint128,
+    /// Whether balance covers liabilities, only given when all markets are counted
+    pub solvent: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolvencyResponse {
+    /// Markets with same bet asset are summed up
+    pub assets: Vec<AssetSolvencyResponse>,
+    /// Last market counted, to query next markets after it
+    pub last_market_id: u64,
+    pub market_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimersResponse {
+    pub user: HumanAddr,
+    pub claimers: Vec<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralStatsResponse {
+    pub referrer: HumanAddr,
+    /// Number of users referred by referrer in all markets
+    pub referred_users: u64,
+    /// Total bet amount of referred users in market
+    pub referred_amount: Uint128,
+    pub claimed_amount: Uint128,
+    /// Rewards of settled rounds which are not claimed yet, up to 30 rounds
+    pub claimable_amount: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -95,4 +451,101 @@ pub struct ConfigResponse {
 pub enum Position {
     UP,
     DOWN,
+    /// Index of price change bucket in range market
+    #[serde(rename = "bucket")]
+    BUCKET(u8),
+    /// Unchanged price, only bettable with draw policy
+    #[serde(rename = "draw")]
+    DRAW,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeMode {
+    /// Fee is taken from total bet amount of round
+    OnPool,
+    /// Fee is taken from bet amount of losing positions
+    OnProfit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawPolicy {
+    /// Refund all bets without fee
+    Refund,
+    /// Entire pool is taken as fee
+    HouseWins,
+    /// Bets on DRAW position win
+    Draw,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderBy {
+    Asc,
+    Desc,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BetResult {
+    Won,
+    Lost,
+    Refund,
+    Pending,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserBetResponse {
+    pub epoch: Uint128,
+    pub amount: Uint128,
+    pub position: Position,
+    pub claimed: bool,
+    pub result: BetResult,
+    pub claimable_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserBetsResponse {
+    pub bets: Vec<UserBetResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundStatus {
+    Betting,
+    Locked,
+    Closed,
+    Refund,
+    OneSided,
+    NoWinner,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundResponse {
+    pub epoch: Uint128,
+    pub start_time: u64,
+    pub lock_time: u64,
+    pub end_time: u64,
+    pub open_price: Option<Uint128>,
+    pub close_price: Option<Uint128>,
+    pub total_amount: Uint128,
+    pub reward_amount: Uint128,
+    pub up_amount: Uint128,
+    pub down_amount: Uint128,
+    pub draw_amount: Uint128,
+    pub bucket_amounts: Vec<Uint128>,
+    pub winning_bucket: Option<u8>,
+    pub draw_policy: DrawPolicy,
+    pub status: RoundStatus,
+    pub executor: Option<HumanAddr>,
+    pub bounty: Uint128,
+    /// Total amount paid to bettors, including reward dust added to fee
+    pub paid_out: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundsResponse {
+    pub rounds: Vec<RoundResponse>,
 }